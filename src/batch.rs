@@ -0,0 +1,260 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::env::{action_mask, step_episode, EnvConfig, Reward, ScoreDelta, NUM_ACTIONS};
+use crate::game_state::GameState;
+use crate::location::Direction;
+use crate::obs::{ObsValue, OBS_LEN};
+
+/// The outcome of a step of one game in a [`BatchGame`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BatchStep {
+    /// The reward for the step
+    pub reward: f32,
+    /// Whether Pacman ran out of lives
+    pub terminated: bool,
+    /// Whether the episode was cut short by the step limit
+    pub truncated: bool,
+}
+
+/// Many independent games, which are stepped together.
+///
+/// Each step moves Pacman and advances to the next update in every game, like
+/// [`PacmanEnv::step`](crate::env::PacmanEnv::step). With the `rayon` feature, the games
+/// are stepped and encoded in parallel.
+///
+/// Each game has its own copy of the reward function, so rewards are decided exactly as
+/// in a [`PacmanEnv`](crate::env::PacmanEnv) with the same reward.
+///
+/// By default, a game that ends is reset straight away, so the observations after a
+/// step are of the new episode. The final state of the old episode is kept until the
+/// next step (see [`BatchGame::final_states`]). New episodes are given consecutive
+/// seeds, so a batch is deterministic given its seed and actions.
+#[derive(Clone, Debug)]
+pub struct BatchGame<R: Reward = ScoreDelta> {
+    games: Vec<GameState>,
+    steps: Vec<u32>,
+    rewards: Vec<R>,
+    results: Vec<BatchStep>,
+    final_states: Vec<Option<GameState>>,
+    config: EnvConfig,
+    next_seed: u64,
+    auto_reset: bool,
+}
+
+// Step a single game, keeping track of the number of steps in its episode
+fn step_game<R: Reward>(
+    game: &mut GameState,
+    steps: &mut u32,
+    reward: &mut R,
+    action: Direction,
+    max_steps: Option<u32>,
+) -> BatchStep {
+    let (reward, terminated, truncated, _) = step_episode(game, steps, reward, action, max_steps);
+    BatchStep {
+        reward,
+        terminated,
+        truncated,
+    }
+}
+
+impl BatchGame<ScoreDelta> {
+    /// Creates a batch of games where the reward is the change in score, the first of
+    /// which starts with the given seed, the next with the seed after it, and so on.
+    pub fn new(num_games: usize, seed: u64, config: EnvConfig) -> Self {
+        Self::with_reward(num_games, seed, config, ScoreDelta)
+    }
+}
+
+impl<R: Reward> BatchGame<R> {
+    /// Creates a batch of games with a custom reward, which is cloned for each game (see
+    /// [`BatchGame::new`]).
+    pub fn with_reward(num_games: usize, seed: u64, config: EnvConfig, reward: R) -> Self
+    where
+        R: Clone,
+    {
+        let mut batch = Self {
+            games: Vec::with_capacity(num_games),
+            steps: vec![0; num_games],
+            rewards: vec![reward; num_games],
+            results: vec![BatchStep::default(); num_games],
+            final_states: vec![None; num_games],
+            config,
+            next_seed: seed,
+            auto_reset: true,
+        };
+        for _ in 0..num_games {
+            let game = batch.new_game();
+            batch.games.push(game);
+        }
+        batch
+    }
+
+    // Start a new episode with the next seed
+    fn new_game(&mut self) -> GameState {
+        let mut game = GameState::new_with_config(self.next_seed, self.config.game);
+        game.paused = false;
+        self.next_seed = self.next_seed.wrapping_add(1);
+        game
+    }
+
+    /// The number of games
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Whether there are no games
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// The games
+    pub fn games(&self) -> &[GameState] {
+        &self.games
+    }
+
+    /// The games, mutably
+    pub fn games_mut(&mut self) -> &mut [GameState] {
+        &mut self.games
+    }
+
+    /// The number of steps taken so far in each game's episode
+    pub fn steps(&self) -> &[u32] {
+        &self.steps
+    }
+
+    /// Each game's reward function
+    pub fn rewards(&self) -> &[R] {
+        &self.rewards
+    }
+
+    /// Each game's reward function, mutably, to inspect or change any state it keeps
+    pub fn rewards_mut(&mut self) -> &mut [R] {
+        &mut self.rewards
+    }
+
+    /// The state that each game was left in by the last step, if it ended an episode and
+    /// the game was then reset, which is needed to bootstrap from truncated episodes
+    pub fn final_states(&self) -> &[Option<GameState>] {
+        &self.final_states
+    }
+
+    /// Sets whether games are reset as soon as they end (true by default).
+    pub fn set_auto_reset(&mut self, auto_reset: bool) {
+        self.auto_reset = auto_reset;
+    }
+
+    /// Starts a new episode of every game, with seeds counting up from the given seed.
+    pub fn reset(&mut self, seed: u64) {
+        self.next_seed = seed;
+        for i in 0..self.games.len() {
+            self.reset_game(i);
+        }
+        self.final_states.fill(None);
+    }
+
+    /// Starts a new episode of one game, with the next seed.
+    pub fn reset_game(&mut self, i: usize) {
+        self.games[i] = self.new_game();
+        self.steps[i] = 0;
+    }
+
+    /// Takes a step in every game, with one action per game, returning the outcome of
+    /// each step.
+    ///
+    /// The reward functions must be `Send`, so that games can be stepped in parallel.
+    ///
+    /// Panics if there is not exactly one action per game.
+    pub fn step(&mut self, actions: &[Direction]) -> &[BatchStep]
+    where
+        R: Send,
+    {
+        assert_eq!(
+            actions.len(),
+            self.games.len(),
+            "expected one action per game"
+        );
+        let max_steps = self.config.max_steps;
+
+        #[cfg(feature = "rayon")]
+        self.games
+            .par_iter_mut()
+            .zip(&mut self.steps)
+            .zip(&mut self.rewards)
+            .zip(&mut self.results)
+            .zip(actions)
+            .for_each(|((((game, steps), reward), result), &action)| {
+                *result = step_game(game, steps, reward, action, max_steps);
+            });
+
+        #[cfg(not(feature = "rayon"))]
+        for ((((game, steps), reward), result), &action) in self
+            .games
+            .iter_mut()
+            .zip(&mut self.steps)
+            .zip(&mut self.rewards)
+            .zip(&mut self.results)
+            .zip(actions)
+        {
+            *result = step_game(game, steps, reward, action, max_steps);
+        }
+
+        // Resets happen in order, so that the seeds don't depend on scheduling
+        self.final_states.fill(None);
+        if self.auto_reset {
+            for i in 0..self.games.len() {
+                if self.results[i].terminated || self.results[i].truncated {
+                    self.final_states[i] = Some(self.games[i]);
+                    self.reset_game(i);
+                }
+            }
+        }
+        &self.results
+    }
+
+    /// Encodes every game's observation into one buffer, one after another (see
+    /// [`GameState::encode_obs`]).
+    ///
+    /// Panics if the buffer is not [`OBS_LEN`] times the number of games long.
+    pub fn write_obs<T: ObsValue + Send>(&self, buf: &mut [T]) {
+        assert_eq!(buf.len(), self.games.len() * OBS_LEN, "wrong buffer length");
+
+        #[cfg(feature = "rayon")]
+        let chunks = buf.par_chunks_exact_mut(OBS_LEN).zip(&self.games);
+        #[cfg(not(feature = "rayon"))]
+        let chunks = buf.chunks_exact_mut(OBS_LEN).zip(&self.games);
+
+        chunks.for_each(|(obs, game)| game.encode_obs(obs.try_into().unwrap()));
+    }
+
+    /// Encodes every game's observation from the end of the last step, before any reset,
+    /// into one buffer: the final observation of each game that ended an episode, and
+    /// the current observation of the others (see [`BatchGame::write_obs`]).
+    ///
+    /// Panics if the buffer is not [`OBS_LEN`] times the number of games long.
+    pub fn write_final_obs<T: ObsValue>(&self, buf: &mut [T]) {
+        assert_eq!(buf.len(), self.games.len() * OBS_LEN, "wrong buffer length");
+        let games = self.games.iter().zip(&self.final_states);
+        for (obs, (game, final_state)) in buf.chunks_exact_mut(OBS_LEN).zip(games) {
+            final_state
+                .as_ref()
+                .unwrap_or(game)
+                .encode_obs(obs.try_into().unwrap());
+        }
+    }
+
+    /// Writes every game's action mask into one buffer, one after another (see
+    /// [`PacmanEnv::action_mask`](crate::env::PacmanEnv::action_mask)).
+    ///
+    /// Panics if the buffer is not [`NUM_ACTIONS`] times the number of games long.
+    pub fn write_action_masks(&self, buf: &mut [bool]) {
+        assert_eq!(
+            buf.len(),
+            self.games.len() * NUM_ACTIONS,
+            "wrong buffer length"
+        );
+        for (mask, game) in buf.chunks_exact_mut(NUM_ACTIONS).zip(&self.games) {
+            mask.copy_from_slice(&action_mask(game));
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! A headless game server that speaks the same websocket protocol as the Go server.
+//!
+//! The game advances at the real tick rate, and every connected client is sent the
+//! encoded game state after each tick. Clients may send the same commands that the Go
+//! server accepts (see [`ClientCommand`]).
+//!
+//! Usage: `pacbot-server [--port PORT] [--seed SEED]`
+
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, io, mem};
+
+use pacbot_rs::codec::PACKET_LEN;
+use pacbot_rs::command::ClientCommand;
+use pacbot_rs::game_state::GameState;
+use pacbot_rs::variables::GAME_FPS;
+use tungstenite::handshake::server::{NoCallback, ServerHandshake};
+use tungstenite::handshake::{HandshakeError, MidHandshake};
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{Error, Message, WebSocket};
+
+/// The port that the Go server listens on
+const DEFAULT_PORT: u16 = 3002;
+
+/// How long a new client has to complete the websocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many packets may be queued for a slow client before it is dropped
+const MAX_QUEUED_PACKETS: usize = 64;
+
+struct Args {
+    port: u16,
+    seed: Option<u64>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        port: DEFAULT_PORT,
+        seed: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or(format!("missing value for {name}"));
+        match arg.as_str() {
+            "--port" => {
+                let port = value("--port")?;
+                args.port = port.parse().map_err(|_| format!("invalid port {port:?}"))?;
+            }
+            "--seed" => {
+                let seed = value("--seed")?;
+                args.seed = Some(seed.parse().map_err(|_| format!("invalid seed {seed:?}"))?);
+            }
+            _ => return Err(format!("unknown argument {arg:?}")),
+        }
+    }
+    Ok(args)
+}
+
+// A client that has connected, but has yet to finish the websocket handshake
+struct PendingClient {
+    handshake: MidHandshake<ServerHandshake<TcpStream, NoCallback>>,
+    addr: SocketAddr,
+    deadline: Instant,
+}
+
+// Accept any clients that are waiting to connect, and start their handshakes (which are
+// continued each tick, so that a slow client never holds up the game)
+fn accept_clients(listener: &TcpListener, pending: &mut Vec<PendingClient>) {
+    loop {
+        let (stream, addr) = match listener.accept() {
+            Ok(conn) => conn,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                eprintln!("Failed to accept client: {e}");
+                return;
+            }
+        };
+        if let Err(e) = stream.set_nonblocking(true) {
+            eprintln!("Failed to set up {addr}: {e}");
+            continue;
+        }
+
+        let config = WebSocketConfig {
+            write_buffer_size: 0,
+            max_write_buffer_size: MAX_QUEUED_PACKETS * PACKET_LEN,
+            ..Default::default()
+        };
+        pending.push(PendingClient {
+            handshake: ServerHandshake::start(stream, NoCallback, Some(config)),
+            addr,
+            deadline: Instant::now() + HANDSHAKE_TIMEOUT,
+        });
+    }
+}
+
+// Continue every pending handshake as far as it can go without blocking, moving clients
+// that finish to the connected clients, and dropping clients that are out of time
+fn continue_handshakes(pending: &mut Vec<PendingClient>, clients: &mut Vec<WebSocket<TcpStream>>) {
+    for client in mem::take(pending) {
+        let addr = client.addr;
+        match client.handshake.handshake() {
+            Ok(ws) => {
+                println!("Client connected: {addr}");
+                clients.push(ws);
+            }
+            Err(HandshakeError::Interrupted(handshake)) if Instant::now() < client.deadline => {
+                pending.push(PendingClient {
+                    handshake,
+                    ..client
+                });
+            }
+            Err(HandshakeError::Interrupted(_)) => eprintln!("Handshake with {addr} timed out"),
+            Err(HandshakeError::Failure(e)) => eprintln!("Handshake with {addr} failed: {e}"),
+        }
+    }
+}
+
+// Apply every command that the client has sent, returning whether it is still connected
+fn read_commands(client: &mut WebSocket<TcpStream>, state: &mut GameState) -> bool {
+    loop {
+        let bytes = match client.read() {
+            Ok(Message::Binary(bytes)) => bytes,
+            Ok(Message::Text(text)) => text.into_bytes(),
+            Ok(_) => continue,
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => return true,
+            Err(_) => return false,
+        };
+        match ClientCommand::from_bytes(&bytes) {
+            Ok(command) => {
+                if let Err(e) = state.apply_command(command) {
+                    eprintln!("Ignoring command {command:?}: {e}");
+                }
+            }
+            Err(e) => eprintln!("Ignoring invalid command {bytes:?}: {e}"),
+        }
+    }
+}
+
+// Send a packet to the client, returning whether it is still connected
+fn send_packet(client: &mut WebSocket<TcpStream>, packet: &[u8]) -> bool {
+    match client.send(Message::Binary(packet.to_vec())) {
+        // The packet was queued, and will be sent along with the next one
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => true,
+        result => result.is_ok(),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("Usage: pacbot-server [--port PORT] [--seed SEED]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, args.port))
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+    {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on port {}: {e}", args.port);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Listening on ws://{}", listener.local_addr().unwrap());
+
+    let mut state = match args.seed {
+        Some(seed) => GameState::new_with_seed(seed),
+        None => GameState::new(),
+    };
+    let mut pending = Vec::new();
+    let mut clients = Vec::new();
+
+    let tick_duration = Duration::from_secs(1) / GAME_FPS as u32;
+    let mut next_tick = Instant::now();
+    loop {
+        accept_clients(&listener, &mut pending);
+        continue_handshakes(&mut pending, &mut clients);
+        clients.retain_mut(|client| read_commands(client, &mut state));
+
+        // The game only advances while it is unpaused, but is always broadcast
+        if !state.paused {
+            state.step();
+        }
+        let packet = state.to_packet();
+        clients.retain_mut(|client| send_packet(client, &packet));
+
+        // Like a ticker, skip any ticks that were missed rather than catching up
+        next_tick += tick_duration;
+        let now = Instant::now();
+        if next_tick < now {
+            next_tick = now;
+        }
+        thread::sleep(next_tick - now);
+    }
+}
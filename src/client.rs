@@ -0,0 +1,326 @@
+//! An async websocket client that keeps a local copy of the game state in sync with
+//! the game server.
+//!
+//! [`Client::connect`] must be called from within a tokio runtime. The connection is
+//! served by a background task, which reconnects (if enabled) whenever the connection
+//! drops, and stops once the [`Client`] is dropped.
+
+use core::fmt;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::codec::DecodeError;
+use crate::command::ClientCommand;
+use crate::game_state::GameState;
+
+/// The number of decoded states that may be waiting to be read before the client stops
+/// reading from the server
+const STATE_BUFFER_LEN: usize = 64;
+
+type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// An error encountered by the client.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection to the server could not be established
+    Connect(Box<tungstenite::Error>),
+    /// The client has stopped, so commands can no longer be sent
+    Closed,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Connect(e) => write!(f, "failed to connect to the server: {e}"),
+            ClientError::Closed => write!(f, "the client has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Connect(e) => Some(e.as_ref()),
+            ClientError::Closed => None,
+        }
+    }
+}
+
+/// Options for how the client behaves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClientOptions {
+    /// The seed that decoded game states are given, used to generate ghost moves
+    pub seed: u64,
+    /// How long to wait between attempts to reconnect, or `None` to stop when the
+    /// connection drops
+    pub reconnect_delay: Option<Duration>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            reconnect_delay: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// Sends commands to the server.
+///
+/// Commands sent while the client is reconnecting are sent once it has reconnected. A
+/// command that was being sent when the connection dropped is sent again after
+/// reconnecting, so it may reach the server twice.
+#[derive(Clone, Debug)]
+pub struct CommandSender {
+    commands: mpsc::UnboundedSender<ClientCommand>,
+}
+
+impl CommandSender {
+    /// Queues a command to be sent to the server.
+    pub fn send(&self, command: ClientCommand) -> Result<(), ClientError> {
+        self.commands.send(command).map_err(|_| ClientError::Closed)
+    }
+}
+
+/// A connection to the game server, which is a stream of the game states that it sends.
+///
+/// Packets that fail to decode are reported as errors, and do not end the stream. The
+/// stream ends once the client stops, which only happens if reconnecting is disabled.
+#[derive(Debug)]
+pub struct Client {
+    states: mpsc::Receiver<Result<GameState, DecodeError>>,
+    commands: CommandSender,
+}
+
+impl Client {
+    /// Connects to the server at the given url, with default options.
+    pub async fn connect(url: &str) -> Result<Self, ClientError> {
+        Self::connect_with_options(url, ClientOptions::default()).await
+    }
+
+    /// Connects to the server at the given url.
+    ///
+    /// Only the first connection attempt is reported as an error; after that, the client
+    /// reconnects in the background.
+    pub async fn connect_with_options(
+        url: &str,
+        options: ClientOptions,
+    ) -> Result<Self, ClientError> {
+        let (conn, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .map_err(|e| ClientError::Connect(Box::new(e)))?;
+
+        let (states_tx, states) = mpsc::channel(STATE_BUFFER_LEN);
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(url.to_owned(), conn, options, states_tx, commands_rx));
+
+        Ok(Self {
+            states,
+            commands: CommandSender {
+                commands: commands_tx,
+            },
+        })
+    }
+
+    /// Waits for the next state from the server, returning `None` once the client stops.
+    pub async fn next_state(&mut self) -> Option<Result<GameState, DecodeError>> {
+        self.states.recv().await
+    }
+
+    /// Queues a command to be sent to the server.
+    pub fn send(&self, command: ClientCommand) -> Result<(), ClientError> {
+        self.commands.send(command)
+    }
+
+    /// A handle that can send commands from elsewhere, such as another task
+    pub fn command_sender(&self) -> CommandSender {
+        self.commands.clone()
+    }
+}
+
+impl Stream for Client {
+    type Item = Result<GameState, DecodeError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.states.poll_recv(cx)
+    }
+}
+
+// Serve connections until the client is dropped, or the connection drops and
+// reconnecting is disabled
+async fn run(
+    url: String,
+    mut conn: Connection,
+    options: ClientOptions,
+    states: mpsc::Sender<Result<GameState, DecodeError>>,
+    mut commands: mpsc::UnboundedReceiver<ClientCommand>,
+) {
+    // A command that could not be sent before the connection dropped
+    let mut unsent = None;
+    loop {
+        if serve(&mut conn, options.seed, &states, &mut commands, &mut unsent).await {
+            let _ = conn.close(None).await;
+            return;
+        }
+        let Some(delay) = options.reconnect_delay else {
+            return;
+        };
+        conn = loop {
+            tokio::time::sleep(delay).await;
+            if states.is_closed() {
+                return;
+            }
+            if let Ok((conn, _)) = tokio_tungstenite::connect_async(&url).await {
+                break conn;
+            }
+        };
+    }
+}
+
+// Pass states and commands along until the connection drops, returning whether the client
+// was dropped. A command that fails to send is left in `unsent`, to be sent first on the
+// next connection.
+async fn serve(
+    conn: &mut Connection,
+    seed: u64,
+    states: &mpsc::Sender<Result<GameState, DecodeError>>,
+    commands: &mut mpsc::UnboundedReceiver<ClientCommand>,
+    unsent: &mut Option<ClientCommand>,
+) -> bool {
+    if let Some(command) = *unsent {
+        if conn
+            .send(Message::Binary(command.to_bytes()))
+            .await
+            .is_err()
+        {
+            return false;
+        }
+        *unsent = None;
+    }
+
+    // A decoded state waiting for room in the buffer, during which no more are read, but
+    // commands are still sent
+    let mut state = None;
+    let mut commands_open = true;
+    loop {
+        tokio::select! {
+            message = conn.next(), if state.is_none() => match message {
+                Some(Ok(Message::Binary(bytes))) => {
+                    state = Some(GameState::from_bytes(&bytes, seed));
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return false,
+            },
+            permit = states.reserve(), if state.is_some() => match permit {
+                Ok(permit) => permit.send(state.take().unwrap()),
+                Err(_) => return true,
+            },
+            command = commands.recv(), if commands_open => match command {
+                Some(command) => {
+                    if conn.send(Message::Binary(command.to_bytes())).await.is_err() {
+                        *unsent = Some(command);
+                        return false;
+                    }
+                }
+                // Every sender was dropped, but states are still wanted
+                None => commands_open = false,
+            },
+            _ = states.closed() => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    // Accept the next websocket connection on a local listener, acting as the server
+    async fn accept(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio_tungstenite::accept_async(stream).await.unwrap()
+    }
+
+    // Send a state from the server, and check that the client decodes it
+    async fn check_state(server: &mut WebSocketStream<TcpStream>, client: &mut Client, score: u16) {
+        let mut state = GameState::new_with_seed(1);
+        state.curr_score = score;
+        let packet = state.to_packet();
+        server.send(Message::Binary(packet.to_vec())).await.unwrap();
+
+        let received = client.next_state().await.unwrap().unwrap();
+        assert_eq!(received.to_packet(), packet);
+        assert_eq!(received.seed, 5);
+    }
+
+    // Send a command from the client, and check that the server receives it
+    async fn check_command(server: &mut WebSocketStream<TcpStream>, client: &Client) {
+        client
+            .send(ClientCommand::SetLocation { row: 23, col: 10 })
+            .unwrap();
+        let message = server.next().await.unwrap().unwrap();
+        assert_eq!(
+            ClientCommand::from_bytes(&message.into_data()),
+            Ok(ClientCommand::SetLocation { row: 23, col: 10 })
+        );
+    }
+
+    #[tokio::test]
+    async fn syncs_with_server_and_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let options = ClientOptions {
+            seed: 5,
+            reconnect_delay: Some(Duration::from_millis(10)),
+        };
+        let (client, mut server) = tokio::join!(
+            Client::connect_with_options(&url, options),
+            accept(&listener)
+        );
+        let mut client = client.unwrap();
+
+        check_state(&mut server, &mut client, 120).await;
+        check_command(&mut server, &client).await;
+
+        // Packets that fail to decode are reported without ending the stream
+        server.send(Message::Binary(vec![0; 3])).await.unwrap();
+        assert!(client.next_state().await.unwrap().is_err());
+
+        // Once the server drops the connection, the client connects again
+        drop(server);
+        let mut server = accept(&listener).await;
+        check_state(&mut server, &mut client, 250).await;
+        check_command(&mut server, &client).await;
+    }
+
+    #[tokio::test]
+    async fn sends_commands_while_states_wait_to_be_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (client, mut server) = tokio::join!(Client::connect(&url), accept(&listener));
+        let mut client = client.unwrap();
+
+        // Send more states than the client buffers, and give it time to fill its buffer
+        let packet = GameState::new_with_seed(1).to_packet();
+        for _ in 0..STATE_BUFFER_LEN + 10 {
+            server.send(Message::Binary(packet.to_vec())).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Commands still go through, and then every state is read
+        tokio::time::timeout(Duration::from_secs(5), check_command(&mut server, &client))
+            .await
+            .expect("command was not sent");
+        for _ in 0..STATE_BUFFER_LEN + 10 {
+            client.next_state().await.unwrap().unwrap();
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use core::fmt;
+
+use crate::game_helpers::Position;
+use crate::location::LocationState;
+use crate::variables::{EMPTY_LOC, MAZE_COLS, MAZE_ROWS};
+
+/// The length of a game state packet sent by the server, in bytes
+pub const PACKET_LEN: usize = 159;
+
+/// An error encountered while decoding bytes from the server.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before a field could be read
+    Truncated {
+        field: &'static str,
+        offset: usize,
+        len: usize,
+    },
+    /// A field held a value that does not correspond to any valid state
+    InvalidValue {
+        field: &'static str,
+        offset: usize,
+        value: u8,
+    },
+    /// A location field encoded an impossible direction
+    InvalidLocation {
+        field: &'static str,
+        offset: usize,
+        bytes: [u8; 2],
+    },
+    /// The input was not the expected length (only checked by strict decoding)
+    WrongLength { expected: usize, actual: usize },
+    /// The input was written in a format version that is not supported
+    UnsupportedVersion { version: u8 },
+    /// The input held a custom maze that had not been registered or passed in
+    UnknownMaze,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated { field, offset, len } => write!(
+                f,
+                "input ended at byte {len} while reading {field} at byte {offset}"
+            ),
+            DecodeError::InvalidValue {
+                field,
+                offset,
+                value,
+            } => write!(f, "invalid {field} {value:#04x} at byte {offset}"),
+            DecodeError::InvalidLocation {
+                field,
+                offset,
+                bytes,
+            } => write!(f, "invalid {field} {bytes:02x?} at byte {offset}"),
+            DecodeError::WrongLength { expected, actual } => {
+                write!(f, "expected {expected} bytes, got {actual}")
+            }
+            DecodeError::UnsupportedVersion { version } => {
+                write!(f, "unsupported format version {version}")
+            }
+            DecodeError::UnknownMaze => write!(f, "unknown maze"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// How to treat input that is longer than expected.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Ignore any trailing bytes
+    #[default]
+    Lenient,
+    /// Reject input that is not exactly the expected length
+    Strict,
+}
+
+impl DecodeMode {
+    // Check the length of the input against the expected length, if strict
+    pub(crate) fn check_len(self, bytes: &[u8], expected: usize) -> Result<(), DecodeError> {
+        if self == DecodeMode::Strict && bytes.len() != expected {
+            return Err(DecodeError::WrongLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads big-endian fields from a byte slice, keeping track of the offset for errors.
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    /// The offset of the next byte to be read
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn bytes<const N: usize>(
+        &mut self,
+        field: &'static str,
+    ) -> Result<[u8; N], DecodeError> {
+        let buf = self
+            .bytes
+            .get(self.offset..self.offset + N)
+            .ok_or(DecodeError::Truncated {
+                field,
+                offset: self.offset,
+                len: self.bytes.len(),
+            })?;
+        self.offset += N;
+        Ok(buf.try_into().unwrap())
+    }
+
+    pub(crate) fn u8(&mut self, field: &'static str) -> Result<u8, DecodeError> {
+        Ok(self.bytes::<1>(field)?[0])
+    }
+
+    pub(crate) fn u16(&mut self, field: &'static str) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(self.bytes(field)?))
+    }
+
+    pub(crate) fn u32(&mut self, field: &'static str) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.bytes(field)?))
+    }
+
+    pub(crate) fn u64(&mut self, field: &'static str) -> Result<u64, DecodeError> {
+        Ok(u64::from_be_bytes(self.bytes(field)?))
+    }
+
+    pub(crate) fn i8(&mut self, field: &'static str) -> Result<i8, DecodeError> {
+        Ok(self.u8(field)? as i8)
+    }
+
+    /// Reads a byte and converts it with the given function, reporting `None` as invalid
+    pub(crate) fn parse<T>(
+        &mut self,
+        field: &'static str,
+        f: impl FnOnce(u8) -> Option<T>,
+    ) -> Result<T, DecodeError> {
+        let offset = self.offset;
+        let value = self.u8(field)?;
+        f(value).ok_or(DecodeError::InvalidValue {
+            field,
+            offset,
+            value,
+        })
+    }
+
+    pub(crate) fn location(&mut self, field: &'static str) -> Result<LocationState, DecodeError> {
+        let offset = self.offset;
+        let bytes = self.bytes(field)?;
+        LocationState::from_bytes(bytes).map_err(|e| DecodeError::InvalidLocation {
+            field,
+            offset,
+            bytes: e.bytes,
+        })
+    }
+
+    /// Checks that a position, read as a row byte and a column byte at the given offset,
+    /// is empty or an open cell, reporting the offending byte otherwise
+    pub(crate) fn check_open(
+        &self,
+        field: &'static str,
+        offset: usize,
+        pos: Position,
+        open: impl FnOnce(Position) -> bool,
+    ) -> Result<(), DecodeError> {
+        if pos == EMPTY_LOC.get_coords() || open(pos) {
+            return Ok(());
+        }
+        // Point at the column if only it is off the maze, and at the row otherwise
+        let row_on_maze = (0..MAZE_ROWS as i8).contains(&pos.0);
+        let col_on_maze = (0..MAZE_COLS as i8).contains(&pos.1);
+        let i = if row_on_maze && !col_on_maze { 1 } else { 0 };
+        Err(DecodeError::InvalidValue {
+            field,
+            offset: offset + i,
+            value: self.bytes[offset + i],
+        })
+    }
+}
+
+/// Writes big-endian fields into a byte slice.
+///
+/// Panics if more bytes are written than the slice can hold.
+pub(crate) struct ByteWriter<'a> {
+    buf: &'a mut [u8],
+    offset: usize,
+}
+
+impl<'a> ByteWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    /// The offset of the next byte to be written
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.offset..self.offset + bytes.len()].copy_from_slice(bytes);
+        self.offset += bytes.len();
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(crate) fn i8(&mut self, value: i8) {
+        self.u8(value as u8);
+    }
+}
@@ -0,0 +1,177 @@
+use core2::io;
+use serde::{Deserialize, Serialize};
+
+use crate::codec::DecodeError;
+use crate::events::GameEvents;
+use crate::game_state::{GameState, LocationError};
+use crate::location::Direction;
+
+/// The length of the longest command, in bytes
+pub const MAX_COMMAND_LEN: usize = 3;
+
+/// A message sent by a client to the game server.
+///
+/// Each command is encoded the same way as the Go server expects:
+///
+/// | Command       | Bytes             |
+/// |---------------|-------------------|
+/// | `Pause`       | `p`               |
+/// | `Resume`      | `P`               |
+/// | `Restart`     | `r`               |
+/// | `MoveUp`      | `w`               |
+/// | `MoveLeft`    | `a`               |
+/// | `MoveDown`    | `s`               |
+/// | `MoveRight`   | `d`               |
+/// | `SetLocation` | `x`, `row`, `col` |
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClientCommand {
+    /// Pause the game
+    Pause,
+    /// Resume (unpause) the game
+    Resume,
+    /// Restart the game from the beginning
+    Restart,
+    /// Move Pacman one space up
+    MoveUp,
+    /// Move Pacman one space left
+    MoveLeft,
+    /// Move Pacman one space down
+    MoveDown,
+    /// Move Pacman one space right
+    MoveRight,
+    /// Update Pacman's location, as measured by the robot
+    SetLocation { row: i8, col: i8 },
+}
+
+impl ClientCommand {
+    /// The command that moves Pacman in the given direction, if there is one
+    pub fn from_direction(dir: Direction) -> Option<Self> {
+        match dir {
+            Direction::Up => Some(ClientCommand::MoveUp),
+            Direction::Left => Some(ClientCommand::MoveLeft),
+            Direction::Down => Some(ClientCommand::MoveDown),
+            Direction::Right => Some(ClientCommand::MoveRight),
+            Direction::Stay => None,
+        }
+    }
+
+    /// The direction that this command moves Pacman in, if it is a move command
+    pub fn direction(&self) -> Option<Direction> {
+        match self {
+            ClientCommand::MoveUp => Some(Direction::Up),
+            ClientCommand::MoveLeft => Some(Direction::Left),
+            ClientCommand::MoveDown => Some(Direction::Down),
+            ClientCommand::MoveRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// Encodes the command into a buffer, returning the encoded bytes.
+    pub fn encode<'a>(&self, buf: &'a mut [u8; MAX_COMMAND_LEN]) -> &'a [u8] {
+        let len = match *self {
+            ClientCommand::SetLocation { row, col } => {
+                *buf = [b'x', row as u8, col as u8];
+                3
+            }
+            _ => {
+                buf[0] = match self {
+                    ClientCommand::Pause => b'p',
+                    ClientCommand::Resume => b'P',
+                    ClientCommand::Restart => b'r',
+                    ClientCommand::MoveUp => b'w',
+                    ClientCommand::MoveLeft => b'a',
+                    ClientCommand::MoveDown => b's',
+                    ClientCommand::MoveRight => b'd',
+                    ClientCommand::SetLocation { .. } => unreachable!(),
+                };
+                1
+            }
+        };
+        &buf[..len]
+    }
+
+    /// Encodes the command.
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode(&mut [0; MAX_COMMAND_LEN]).to_vec()
+    }
+
+    /// Writes the encoded command to a writer.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.encode(&mut [0; MAX_COMMAND_LEN]))
+    }
+
+    /// Decodes a command, rejecting messages that are not exactly the expected length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let command = match bytes.first() {
+            None => {
+                return Err(DecodeError::Truncated {
+                    field: "command",
+                    offset: 0,
+                    len: 0,
+                })
+            }
+            Some(b'p') => ClientCommand::Pause,
+            Some(b'P') => ClientCommand::Resume,
+            Some(b'r') => ClientCommand::Restart,
+            Some(b'w') => ClientCommand::MoveUp,
+            Some(b'a') => ClientCommand::MoveLeft,
+            Some(b's') => ClientCommand::MoveDown,
+            Some(b'd') => ClientCommand::MoveRight,
+            Some(b'x') => match *bytes {
+                [_, row, col, ..] => ClientCommand::SetLocation {
+                    row: row as i8,
+                    col: col as i8,
+                },
+                _ => {
+                    return Err(DecodeError::Truncated {
+                        field: "location",
+                        offset: 1,
+                        len: bytes.len(),
+                    })
+                }
+            },
+            Some(&value) => {
+                return Err(DecodeError::InvalidValue {
+                    field: "command",
+                    offset: 0,
+                    value,
+                })
+            }
+        };
+
+        let expected = command.encode(&mut [0; MAX_COMMAND_LEN]).len();
+        if bytes.len() != expected {
+            return Err(DecodeError::WrongLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(command)
+    }
+}
+
+impl GameState {
+    /// Apply a command from a client, the same way the server does, returning the
+    /// events that happened.
+    ///
+    /// Returns an error, and leaves the game unchanged, if the command sets a location
+    /// that Pacman can't be moved to (see [`GameState::set_pacman_location`]).
+    pub fn apply_command(&mut self, command: ClientCommand) -> Result<GameEvents, LocationError> {
+        match command {
+            ClientCommand::Pause => self.paused = true,
+            ClientCommand::Resume => self.paused = false,
+            ClientCommand::Restart => *self = GameState::new_with_config(self.seed, self.config),
+            ClientCommand::MoveUp
+            | ClientCommand::MoveLeft
+            | ClientCommand::MoveDown
+            | ClientCommand::MoveRight => {
+                return Ok(self.move_pacman_dir(command.direction().unwrap()));
+            }
+            ClientCommand::SetLocation { row, col } => {
+                return self.set_pacman_location((row, col));
+            }
+        }
+        Ok(GameEvents::new())
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_modes::GameMode,
+    location::LocationState,
+    maze::{Maze, STANDARD_MAZE},
+    variables::*,
+};
+
+/// The rules that a game is played with.
+///
+/// The default configuration matches the constants in [`crate::variables`], which
+/// follow the official competition rules. Each [`crate::game_state::GameState`] carries
+/// its own configuration, so several rule versions can be simulated side by side.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, PartialEq)]
+pub struct GameConfig {
+    /// The update period that the game starts with
+    pub init_update_period: u8,
+
+    /// The number of steps (update periods) that pass before the level speeds up
+    pub level_duration: u16,
+
+    /// The number of steps (update periods) before a level speeds up further
+    pub level_penalty_duration: u16,
+
+    /// The mode that the game starts on
+    pub init_mode: GameMode,
+
+    /// The number of steps (update periods) spent in scatter mode
+    pub scatter_duration: u8,
+
+    /// The number of steps (update periods) spent in chase mode
+    pub chase_duration: u8,
+
+    /// The level that Pacman starts on
+    pub init_level: u8,
+
+    /// The number of lives that Pacman starts with
+    pub init_lives: u8,
+
+    /// The maze, including spawn points for Pacman, the fruit and the ghosts.
+    ///
+    /// Custom mazes can be given a static lifetime with [`Maze::into_static`]. A config
+    /// can only be deserialized if its maze is the standard maze or has been registered
+    /// (see [`Maze::register`]), which needs `std`.
+    #[serde(with = "maze_ref")]
+    pub maze: &'static Maze,
+
+    /// The number of steps that the fruit stays on the maze for
+    pub fruit_duration: u8,
+
+    /// The points earned upon collecting a fruit
+    pub fruit_points: u16,
+
+    /// Scatter targets for the ghosts, indexed by color
+    pub ghost_scatter_targets: [LocationState; 4],
+
+    /// The number of steps that the ghosts stay in the trapped state for, indexed by color
+    pub ghost_trapped_steps: [u8; 4],
+
+    /// The number of steps that the ghosts stay in the frightened state for
+    pub ghost_fright_steps: u8,
+
+    /// The number of pellets at which to spawn the first fruit
+    pub fruit_threshold1: u16,
+
+    /// The number of pellets at which to spawn the second fruit
+    pub fruit_threshold2: u16,
+
+    /// The number of pellets at which to make the ghosts angry
+    pub anger_threshold1: u16,
+
+    /// The number of pellets at which to make the ghosts angrier
+    pub anger_threshold2: u16,
+
+    /// The points earned when collecting a pellet
+    pub pellet_points: u16,
+
+    /// The points earned when collecting a super pellet
+    pub super_pellet_points: u16,
+
+    /// The multiplier for the combo from catching successive frightened ghosts
+    pub combo_multiplier: u16,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameConfig {
+    /// Creates the standard configuration, matching the competition rules.
+    pub const fn new() -> Self {
+        Self {
+            init_update_period: INIT_UPDATE_PERIOD,
+            level_duration: LEVEL_DURATION,
+            level_penalty_duration: LEVEL_PENALTY_DURATION,
+            init_mode: INIT_MODE,
+            scatter_duration: GameMode::SCATTER.duration(),
+            chase_duration: GameMode::CHASE.duration(),
+            init_level: INIT_LEVEL,
+            init_lives: INIT_LIVES,
+            maze: &STANDARD_MAZE,
+            fruit_duration: FRUIT_DURATION,
+            fruit_points: FRUIT_POINTS,
+            ghost_scatter_targets: GHOST_SCATTER_TARGETS,
+            ghost_trapped_steps: GHOST_TRAPPED_STEPS,
+            ghost_fright_steps: GHOST_FRIGHT_STEPS,
+            fruit_threshold1: FRUIT_THRESHOLD1,
+            fruit_threshold2: FRUIT_THRESHOLD2,
+            anger_threshold1: ANGER_THRESHOLD1,
+            anger_threshold2: ANGER_THRESHOLD2,
+            pellet_points: PELLET_POINTS,
+            super_pellet_points: SUPER_PELLET_POINTS,
+            combo_multiplier: COMBO_MULTIPLIER,
+        }
+    }
+
+    /// Returns the length of the given game mode, in units of steps (update periods).
+    pub const fn mode_duration(&self, mode: GameMode) -> u8 {
+        match mode {
+            GameMode::SCATTER => self.scatter_duration,
+            GameMode::CHASE => self.chase_duration,
+        }
+    }
+}
+
+// Mazes are serialized by value, and deserialized into a shared static maze (which,
+// without std, must be the standard maze)
+mod maze_ref {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::maze::{find_static, Maze};
+
+    pub fn serialize<S: Serializer>(
+        maze: &&'static Maze,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        maze.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static Maze, D::Error> {
+        let maze = Maze::deserialize(deserializer)?;
+        find_static(&maze, &[]).ok_or_else(|| D::Error::custom("unknown maze"))
+    }
+}
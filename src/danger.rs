@@ -0,0 +1,191 @@
+use crate::events::GameEvents;
+use crate::game_helpers::{GhostPlan, Position};
+use crate::game_state::GameState;
+use crate::ghost_state::GhostColor;
+use crate::location::{Direction, LocationState};
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+/*
+The danger map searches over every way that each ghost could move, one update at a
+time. A ghost's state is its cell and its heading (the direction of its next move),
+since ghosts never turn around of their own accord: at each update, a ghost moves
+along its heading and then picks any new heading except straight back.
+
+When ghosts do turn around, or stay put, is decided by timers rather than by where
+they are, so a copy of the game is stepped alongside the search (as in ghost
+prediction) to find out how the engine plans each ghost at each update. Ghosts that
+are trapped turn around, and ghosts that the engine skips stay where they are,
+exactly as they would in the game. This also covers the reversals when the mode
+changes, and tells when frightened ghosts become dangerous again.
+
+The search allows every heading that the ghost could pick, not just the one that it
+would pick, so arrival times are lower bounds. They assume that Pacman doesn't catch
+any ghosts or eat a super pellet in the meantime.
+*/
+
+/// When a ghost can first reach a cell, in a [`DangerMap`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Arrival {
+    /// The number of updates from now (0 if the ghost is already there)
+    pub steps: u8,
+    /// The first ghost to arrive, preferring the order red, pink, cyan, orange on ties
+    pub ghost: GhostColor,
+}
+
+/// The earliest update at which any ghost that is not frightened can reach each cell
+/// that Pacman can walk on, created by [`GameState::danger_map`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DangerMap {
+    arrivals: [[Option<Arrival>; MAZE_COLS]; MAZE_ROWS],
+    max_steps: u8,
+}
+
+impl DangerMap {
+    /// When a ghost can first reach a cell, or `None` if no ghost can reach it within
+    /// [`DangerMap::max_steps`] updates (or Pacman can't walk there)
+    pub fn arrival(&self, pos: Position) -> Option<Arrival> {
+        let (row, col) = pos;
+        if row < 0 || col < 0 {
+            return None;
+        }
+        *self.arrivals.get(row as usize)?.get(col as usize)?
+    }
+
+    /// The number of updates until a ghost can first reach a cell (see
+    /// [`DangerMap::arrival`])
+    pub fn steps(&self, pos: Position) -> Option<u8> {
+        self.arrival(pos).map(|arrival| arrival.steps)
+    }
+
+    /// The first ghost that can reach a cell (see [`DangerMap::arrival`])
+    pub fn ghost(&self, pos: Position) -> Option<GhostColor> {
+        self.arrival(pos).map(|arrival| arrival.ghost)
+    }
+
+    /// How many updates ahead the map looks
+    pub fn max_steps(&self) -> u8 {
+        self.max_steps
+    }
+}
+
+// The headings that a ghost could have on each cell, as bits indexed by direction
+type Headings = [[u8; MAZE_COLS]; MAZE_ROWS];
+
+// Every heading that a ghost can have (a ghost that stays is heading nowhere)
+const HEADINGS: [Direction; 5] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+    Direction::Right,
+    Direction::Stay,
+];
+
+// Add a heading to a cell, if it is on the maze
+fn add_heading(headings: &mut Headings, pos: Position, dir: Direction) {
+    let (row, col) = pos;
+    if (0..MAZE_ROWS as i8).contains(&row) && (0..MAZE_COLS as i8).contains(&col) {
+        headings[row as usize][col as usize] |= 1 << dir as u8;
+    }
+}
+
+impl GameState {
+    // Determines if a ghost can move into a cell, allowing spawning ghosts to move
+    // through the ghost house
+    fn ghost_can_enter(&self, pos: Position, spawning: bool) -> bool {
+        let in_house = self.ghost_spawn_at(pos) || pos == self.config.maze.ghost_house_exit_pos;
+        !self.wall_at(pos) || (spawning && in_house)
+    }
+
+    // Move a ghost one update along every heading it could have, as it was planned
+    fn advance_headings(&self, headings: &Headings, plan: GhostPlan, spawning: bool) -> Headings {
+        let mut next = [[0; MAZE_COLS]; MAZE_ROWS];
+        for (row, cells) in headings.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                for dir in HEADINGS {
+                    if cell & (1 << dir as u8) == 0 {
+                        continue;
+                    }
+                    let loc = LocationState::new(row as i8, col as i8, dir);
+                    let pos = loc.get_ahead_coords(1);
+                    match plan {
+                        GhostPlan::Skipped => add_heading(&mut next, loc.get_coords(), dir),
+                        GhostPlan::Reversed => add_heading(&mut next, pos, dir.opposite()),
+                        GhostPlan::Targeted | GhostPlan::Random => {
+                            let next_loc = LocationState::new(pos.0, pos.1, dir);
+                            for new_dir in Direction::all_except_stay() {
+                                let turn_pos = next_loc.get_neighbor_coords(new_dir);
+                                if new_dir != dir.opposite()
+                                    && self.ghost_can_enter(turn_pos, spawning)
+                                {
+                                    add_heading(&mut next, pos, new_dir);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        next
+    }
+
+    /// Computes when the ghosts can first reach each cell, looking up to `max_steps`
+    /// updates ahead, without changing the game state.
+    ///
+    /// Ghosts only count once they are no longer frightened, and they can't turn around
+    /// except when the engine makes them (see [`DangerMap`]).
+    pub fn danger_map(&self, max_steps: u8) -> DangerMap {
+        let mut map = DangerMap {
+            arrivals: [[None; MAZE_COLS]; MAZE_ROWS],
+            max_steps,
+        };
+        let mut record = |headings: &[Headings; 4], frightened: [bool; 4], steps: u8| {
+            for (ghost, (headings, frightened)) in headings.iter().zip(frightened).enumerate() {
+                if frightened {
+                    continue;
+                }
+                for (row, cells) in headings.iter().enumerate() {
+                    for (col, &cell) in cells.iter().enumerate() {
+                        let arrival = &mut map.arrivals[row][col];
+                        if cell != 0 && arrival.is_none() && !self.wall_at((row as i8, col as i8)) {
+                            *arrival = Some(Arrival {
+                                steps,
+                                ghost: (ghost as u8).try_into().unwrap(),
+                            });
+                        }
+                    }
+                }
+            }
+        };
+
+        // The ghosts are where they are now, and will be where they have planned to go
+        let mut headings = [[[0; MAZE_COLS]; MAZE_ROWS]; 4];
+        for (headings, ghost) in headings.iter_mut().zip(&self.ghosts) {
+            add_heading(headings, ghost.loc.get_coords(), ghost.loc.dir);
+        }
+        record(&headings, self.ghosts.map(|g| g.is_frightened()), 0);
+
+        let spawning = self.ghosts.map(|g| g.spawning);
+        let mut headings = [[[0; MAZE_COLS]; MAZE_ROWS]; 4];
+        for (headings, ghost) in headings.iter_mut().zip(&self.ghosts) {
+            add_heading(headings, ghost.next_loc.get_coords(), ghost.next_loc.dir);
+        }
+
+        let mut state = *self;
+        for steps in 1..=max_steps {
+            // Step the copy to this update, then find out how each ghost is planned
+            state.update_all_ghosts();
+            state.try_respawn_pacman();
+            state.handle_step_events(&mut GameEvents::new());
+            record(&headings, state.ghosts.map(|g| g.is_frightened()), steps);
+            if steps == max_steps {
+                break;
+            }
+            let plans = state.plan_ghosts();
+            for ghost in 0..4 {
+                headings[ghost] =
+                    self.advance_headings(&headings[ghost], plans[ghost], spawning[ghost]);
+            }
+        }
+        map
+    }
+}
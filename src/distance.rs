@@ -0,0 +1,250 @@
+use crate::game_helpers::Position;
+use crate::location::Direction;
+use crate::maze::Maze;
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+/// The number of cells that Pacman can walk on in the standard maze
+pub const STANDARD_CELLS: usize = 288;
+
+// The index of a cell that is not walkable
+const NO_CELL: u16 = u16::MAX;
+
+// The distance between cells that cannot reach each other
+const UNREACHABLE: u8 = u8::MAX;
+
+/*
+The table numbers the walkable cells of a maze, and holds the length of the shortest
+path between every pair of them, found with one breadth-first search per cell. Only
+distances are stored: the first step of a shortest path is to whichever neighbor is
+one step closer to the target, which takes at most four lookups to find.
+
+Distances are measured the way Pacman moves, so the ghost house counts as a wall.
+*/
+
+/// Precomputed shortest-path distances between every pair of walkable cells in a maze.
+///
+/// `N` is the largest number of walkable cells that the table can hold; it is
+/// [`STANDARD_CELLS`] by default, which is enough for the standard maze. The table is
+/// `N * N` bytes, so tables are best kept in a `static` or a `Box`.
+///
+/// With `std`, the table for the standard maze is built the first time
+/// [`DistanceTable::standard`] is called. Without `std`, [`DistanceTable::new`] can be
+/// evaluated at compile time instead, to build a table into a `static` (which takes a
+/// few seconds of compile time).
+#[derive(Clone, Debug)]
+pub struct DistanceTable<const N: usize = STANDARD_CELLS> {
+    index: [[u16; MAZE_COLS]; MAZE_ROWS],
+    cells: [Position; N],
+    num_cells: usize,
+    distances: [[u8; N]; N],
+}
+
+// Determines if Pacman can walk on a cell of a maze
+const fn walkable(maze: &Maze, pos: Position) -> bool {
+    let (row, col) = pos;
+    if row < 0 || row >= MAZE_ROWS as i8 || col < 0 || col >= MAZE_COLS as i8 {
+        return false;
+    }
+    (maze.walls[row as usize] >> col as usize) & 1 == 0
+}
+
+impl DistanceTable {
+    /// The table for the standard maze, which is built on first use.
+    #[cfg(feature = "std")]
+    pub fn standard() -> &'static DistanceTable {
+        static TABLE: std::sync::OnceLock<Box<DistanceTable>> = std::sync::OnceLock::new();
+        TABLE.get_or_init(|| Box::new(DistanceTable::new(&crate::maze::STANDARD_MAZE).unwrap()))
+    }
+}
+
+impl<const N: usize> DistanceTable<N> {
+    /// Computes the distances between every pair of walkable cells in a maze, or returns
+    /// `None` if the maze has more than `N` walkable cells, or if any shortest path is
+    /// longer than 254 moves.
+    ///
+    /// This can be evaluated at compile time, to build the table into a `static`.
+    ///
+    /// Panics if `N` is too large to number the cells with a `u16`.
+    pub const fn new(maze: &Maze) -> Option<Self> {
+        assert!(N < NO_CELL as usize, "too many cells for a distance table");
+
+        // Number the walkable cells
+        let mut index = [[NO_CELL; MAZE_COLS]; MAZE_ROWS];
+        let mut cells = [(0, 0); N];
+        let mut num_cells = 0;
+        let mut row = 0;
+        while row < MAZE_ROWS {
+            let mut col = 0;
+            while col < MAZE_COLS {
+                let pos = (row as i8, col as i8);
+                if walkable(maze, pos) {
+                    if num_cells == N {
+                        return None;
+                    }
+                    index[row][col] = num_cells as u16;
+                    cells[num_cells] = pos;
+                    num_cells += 1;
+                }
+                col += 1;
+            }
+            row += 1;
+        }
+
+        // List the walkable neighbors of each cell
+        let mut neighbors = [[NO_CELL; 4]; N];
+        let dirs = Direction::all_except_stay();
+        let mut cell = 0;
+        while cell < num_cells {
+            let (row, col) = cells[cell];
+            let mut i = 0;
+            while i < dirs.len() {
+                let (d_row, d_col) = dirs[i].vector();
+                let next = (row + d_row, col + d_col);
+                if walkable(maze, next) {
+                    neighbors[cell][i] = index[next.0 as usize][next.1 as usize];
+                }
+                i += 1;
+            }
+            cell += 1;
+        }
+
+        // Search outwards from each cell
+        let mut distances = [[UNREACHABLE; N]; N];
+        let mut queue = [0u16; N];
+        let mut start = 0;
+        while start < num_cells {
+            let row = &mut distances[start];
+            row[start] = 0;
+            queue[0] = start as u16;
+            let (mut head, mut tail) = (0, 1);
+            while head < tail {
+                let cell = queue[head] as usize;
+                head += 1;
+                let mut i = 0;
+                while i < 4 {
+                    let next = neighbors[cell][i];
+                    i += 1;
+                    if next != NO_CELL && row[next as usize] == UNREACHABLE {
+                        // Distances must fit in a byte, below the value for unreachable
+                        if row[cell] + 1 == UNREACHABLE {
+                            return None;
+                        }
+                        row[next as usize] = row[cell] + 1;
+                        queue[tail] = next;
+                        tail += 1;
+                    }
+                }
+            }
+            start += 1;
+        }
+
+        Some(Self {
+            index,
+            cells,
+            num_cells,
+            distances,
+        })
+    }
+
+    /// The number of walkable cells
+    pub fn num_cells(&self) -> usize {
+        self.num_cells
+    }
+
+    /// The walkable cells, in the order that they are numbered
+    pub fn cells(&self) -> &[Position] {
+        &self.cells[..self.num_cells]
+    }
+
+    /// The number of a walkable cell, or `None` if Pacman cannot walk there
+    pub fn cell_index(&self, pos: Position) -> Option<usize> {
+        let (row, col) = pos;
+        if row < 0 || col < 0 {
+            return None;
+        }
+        let index = *self.index.get(row as usize)?.get(col as usize)?;
+        (index != NO_CELL).then_some(index as usize)
+    }
+
+    /// The length of the shortest path between two cells, or `None` if either is not
+    /// walkable or there is no path between them
+    pub fn distance(&self, from: Position, to: Position) -> Option<u8> {
+        let distance = self.distances[self.cell_index(from)?][self.cell_index(to)?];
+        (distance != UNREACHABLE).then_some(distance)
+    }
+
+    /// The first step of a shortest path between two cells, which is
+    /// [`Direction::Stay`] if they are the same cell, or `None` if there is no path.
+    ///
+    /// When several shortest paths exist, directions are preferred in the order up,
+    /// left, down, right.
+    pub fn next_direction(&self, from: Position, to: Position) -> Option<Direction> {
+        let distance = self.distance(from, to)?;
+        if distance == 0 {
+            return Some(Direction::Stay);
+        }
+        Direction::all_except_stay().into_iter().find(|dir| {
+            let (d_row, d_col) = dir.vector();
+            let next = (from.0 + d_row, from.1 + d_col);
+            self.distance(next, to) == Some(distance - 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The standard table, evaluated at compile time as the docs describe
+    static STANDARD: DistanceTable = match DistanceTable::new(&Maze::standard()) {
+        Some(table) => table,
+        None => panic!("the standard maze has too many cells"),
+    };
+
+    // A maze whose only walkable cells are the first `len` cells of a path that winds
+    // back and forth across the rows, so the longest shortest path is `len - 1` moves
+    fn winding_maze(len: usize) -> (Maze, Position, Position) {
+        let mut maze = Maze::standard();
+        maze.walls = [u32::MAX; MAZE_ROWS];
+        let mut path = [(0, 0); MAZE_ROWS * MAZE_COLS];
+        let mut num_cells = 0;
+        for row in (1..MAZE_ROWS - 1).step_by(2) {
+            let forwards = row % 4 == 1;
+            for i in 1..MAZE_COLS - 1 {
+                let col = if forwards { i } else { MAZE_COLS - 1 - i };
+                path[num_cells] = (row, col);
+                num_cells += 1;
+            }
+            // Step down to the next row at the end of this one
+            let end = if forwards { MAZE_COLS - 2 } else { 1 };
+            path[num_cells] = (row + 1, end);
+            num_cells += 1;
+        }
+        for &(row, col) in &path[..len] {
+            maze.walls[row] &= !(1 << col);
+        }
+        let pos = |(row, col): (usize, usize)| (row as i8, col as i8);
+        (maze, pos(path[0]), pos(path[len - 1]))
+    }
+
+    #[test]
+    fn const_table_matches_runtime_table() {
+        let table = DistanceTable::standard();
+        assert_eq!(STANDARD.cells(), table.cells());
+        for &from in table.cells() {
+            for &to in table.cells() {
+                assert_eq!(STANDARD.distance(from, to), table.distance(from, to));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_paths_too_long_for_a_byte() {
+        let (maze, start, end) = winding_maze(255);
+        let table = DistanceTable::<512>::new(&maze).unwrap();
+        assert_eq!(table.distance(start, end), Some(254));
+
+        let (maze, _, _) = winding_maze(256);
+        assert!(DistanceTable::<512>::new(&maze).is_none());
+    }
+}
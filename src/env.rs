@@ -0,0 +1,195 @@
+use crate::config::GameConfig;
+use crate::events::GameEvents;
+use crate::game_state::GameState;
+use crate::location::Direction;
+
+/// The number of actions, which are the directions that Pacman can move in
+pub const NUM_ACTIONS: usize = 5;
+
+/*
+An environment for reinforcement learning, in the style of Gym. Each step moves Pacman
+with the chosen action and then advances the engine to the next update, using
+GameState::step_with_action. Actions are directions, and are indexed by their value
+(so the action mask can be indexed with `dir as usize`).
+*/
+
+// Which actions would move Pacman, with Stay always allowed
+pub(crate) fn action_mask(state: &GameState) -> [bool; NUM_ACTIONS] {
+    let mut mask = [false; NUM_ACTIONS];
+    for dir in Direction::all_except_stay() {
+        let next = state.pacman_loc.get_neighbor_coords(dir);
+        mask[dir as usize] = !state.wall_at(next);
+    }
+    mask[Direction::Stay as usize] = true;
+    mask
+}
+
+/// Decides the reward for a step of a [`PacmanEnv`] (or of a `BatchGame`).
+pub trait Reward {
+    /// The reward for a step from `prev` to `next`, during which the events happened
+    fn reward(&mut self, prev: &GameState, next: &GameState, events: &GameEvents) -> f32;
+}
+
+/// The default reward, which is the change in score.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScoreDelta;
+
+impl Reward for ScoreDelta {
+    fn reward(&mut self, prev: &GameState, next: &GameState, _events: &GameEvents) -> f32 {
+        next.curr_score as f32 - prev.curr_score as f32
+    }
+}
+
+impl<F: FnMut(&GameState, &GameState, &GameEvents) -> f32> Reward for F {
+    fn reward(&mut self, prev: &GameState, next: &GameState, events: &GameEvents) -> f32 {
+        self(prev, next, events)
+    }
+}
+
+// Take a step of an episode, keeping count of its steps, and return the reward, whether
+// the episode terminated or was truncated, and extra information (shared by PacmanEnv
+// and BatchGame, so that they always agree)
+pub(crate) fn step_episode<R: Reward>(
+    state: &mut GameState,
+    steps: &mut u32,
+    reward: &mut R,
+    action: Direction,
+    max_steps: Option<u32>,
+) -> (f32, bool, bool, StepInfo) {
+    let prev = *state;
+    let result = state.step_with_action(action);
+    if result.ticks > 0 {
+        *steps += 1;
+    }
+
+    let reward = reward.reward(&prev, state, &result.events);
+    let terminated = result.game_over;
+    let truncated = !terminated && max_steps.is_some_and(|max| *steps >= max);
+    let info = StepInfo {
+        events: result.events,
+        ticks: result.ticks,
+        steps: *steps,
+    };
+    (reward, terminated, truncated, info)
+}
+
+/// Settings for a [`PacmanEnv`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct EnvConfig {
+    /// The rules that each episode is played with
+    pub game: GameConfig,
+    /// The number of steps after which an episode is truncated, if any
+    pub max_steps: Option<u32>,
+}
+
+/// Extra information about a step of a [`PacmanEnv`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StepInfo {
+    /// The events that happened during the step
+    pub events: GameEvents,
+    /// The number of ticks that the engine advanced
+    pub ticks: u32,
+    /// The number of steps taken so far in the episode
+    pub steps: u32,
+}
+
+/// A reinforcement learning environment, on top of a [`GameState`].
+#[derive(Clone, Debug)]
+pub struct PacmanEnv<R: Reward = ScoreDelta> {
+    state: GameState,
+    config: EnvConfig,
+    reward: R,
+    steps: u32,
+}
+
+// The state that an episode starts from, which is unpaused so that stepping plays it
+fn first_state(seed: u64, config: &EnvConfig) -> GameState {
+    let mut state = GameState::new_with_config(seed, config.game);
+    state.paused = false;
+    state
+}
+
+impl PacmanEnv<ScoreDelta> {
+    /// Creates an environment where the reward is the change in score.
+    ///
+    /// The environment starts with a seed of 0; call [`PacmanEnv::reset`] to choose one.
+    pub fn new(config: EnvConfig) -> Self {
+        Self::with_reward(config, ScoreDelta)
+    }
+}
+
+impl<R: Reward> PacmanEnv<R> {
+    /// Creates an environment with a custom reward.
+    ///
+    /// The environment starts with a seed of 0; call [`PacmanEnv::reset`] to choose one.
+    pub fn with_reward(config: EnvConfig, reward: R) -> Self {
+        Self {
+            state: first_state(0, &config),
+            config,
+            reward,
+            steps: 0,
+        }
+    }
+
+    /// The current state of the game
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The settings of the environment
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The reward function
+    pub fn reward(&self) -> &R {
+        &self.reward
+    }
+
+    /// The reward function, mutably, to inspect or change any state it keeps
+    pub fn reward_mut(&mut self) -> &mut R {
+        &mut self.reward
+    }
+
+    /// The number of steps taken so far in the episode
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Starts a new episode, returning the first observation.
+    pub fn reset(&mut self, seed: u64) -> (GameState, StepInfo) {
+        self.state = first_state(seed, &self.config);
+        self.steps = 0;
+        let info = StepInfo {
+            events: GameEvents::new(),
+            ticks: 0,
+            steps: 0,
+        };
+        (self.state, info)
+    }
+
+    /// Takes a step, returning the observation, the reward, whether the episode has ended
+    /// (`terminated`), whether it was cut short by the step limit (`truncated`), and
+    /// extra information.
+    ///
+    /// An episode terminates when Pacman runs out of lives. Steps taken after that do
+    /// nothing.
+    pub fn step(&mut self, action: Direction) -> (GameState, f32, bool, bool, StepInfo) {
+        let (reward, terminated, truncated, info) = step_episode(
+            &mut self.state,
+            &mut self.steps,
+            &mut self.reward,
+            action,
+            self.config.max_steps,
+        );
+        (self.state, reward, terminated, truncated, info)
+    }
+
+    /// Which actions would move Pacman, indexed by the action's value.
+    ///
+    /// Moving into a wall is allowed, but leaves Pacman where it is, the same as
+    /// [`Direction::Stay`], which is always allowed.
+    pub fn action_mask(&self) -> [bool; NUM_ACTIONS] {
+        action_mask(&self.state)
+    }
+}
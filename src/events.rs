@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game_modes::GameMode, ghost_state::GhostColor};
+
+/// The maximum number of events that a [`GameEvents`] buffer can hold
+pub const EVENTS_CAPACITY: usize = 32;
+
+/// Something notable that happened while the game state was being updated.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum GameEvent {
+    /// Pacman collected a regular pellet at the given position
+    PelletEaten { pos: (i8, i8) },
+    /// Pacman collected a super pellet at the given position, frightening the ghosts
+    SuperPelletEaten { pos: (i8, i8) },
+    /// The fruit appeared on the maze
+    FruitSpawned,
+    /// Pacman collected the fruit
+    FruitEaten { points: u16 },
+    /// Pacman ate a frightened ghost
+    GhostEaten { color: GhostColor, points: u16 },
+    /// Pacman collided with a ghost and lost a life
+    PacmanDied { lives_left: u8 },
+    /// Pacman collected every pellet on the given level
+    LevelCleared { level: u8 },
+    /// The game mode changed
+    ModeChanged { mode: GameMode },
+}
+
+impl GameEvent {
+    /// The name of the kind of event, ex. `"PelletEaten"`
+    pub const fn name(&self) -> &'static str {
+        match self {
+            GameEvent::PelletEaten { .. } => "PelletEaten",
+            GameEvent::SuperPelletEaten { .. } => "SuperPelletEaten",
+            GameEvent::FruitSpawned => "FruitSpawned",
+            GameEvent::FruitEaten { .. } => "FruitEaten",
+            GameEvent::GhostEaten { .. } => "GhostEaten",
+            GameEvent::PacmanDied { .. } => "PacmanDied",
+            GameEvent::LevelCleared { .. } => "LevelCleared",
+            GameEvent::ModeChanged { .. } => "ModeChanged",
+        }
+    }
+}
+
+/// A fixed-capacity list of the events produced by a single update.
+///
+/// Events beyond [`EVENTS_CAPACITY`] are dropped, which is reported by
+/// [`GameEvents::overflowed`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameEvents {
+    events: [Option<GameEvent>; EVENTS_CAPACITY],
+    len: usize,
+    overflowed: bool,
+}
+
+impl Default for GameEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameEvents {
+    /// Creates an empty list of events.
+    pub const fn new() -> Self {
+        Self {
+            events: [None; EVENTS_CAPACITY],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Adds an event to the end of the list, dropping it if the list is full.
+    pub fn push(&mut self, event: GameEvent) {
+        if self.len == EVENTS_CAPACITY {
+            self.overflowed = true;
+            return;
+        }
+        self.events[self.len] = Some(event);
+        self.len += 1;
+    }
+
+    /// Adds all the events of another list to the end of this one.
+    pub fn extend(&mut self, other: &GameEvents) {
+        for event in other {
+            self.push(*event);
+        }
+        self.overflowed |= other.overflowed;
+    }
+
+    /// The names of the events, in order (see [`GameEvent::name`])
+    #[cfg(feature = "std")]
+    pub fn names(&self) -> Vec<String> {
+        self.iter().map(|event| event.name().to_string()).collect()
+    }
+
+    /// The number of events in the list
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no events happened
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether any events were dropped because the list was full
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Whether the list contains the given event
+    pub fn contains(&self, event: &GameEvent) -> bool {
+        self.iter().any(|e| e == event)
+    }
+
+    /// Iterates over the events, in the order that they happened
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> {
+        self.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a GameEvents {
+    type Item = &'a GameEvent;
+    type IntoIter = core::iter::Flatten<core::slice::Iter<'a, Option<GameEvent>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events[..self.len].iter().flatten()
+    }
+}
@@ -2,6 +2,7 @@ use rand::prelude::SmallRng;
 use rand::seq::IteratorRandom;
 use rand::{Rng, SeedableRng};
 
+use crate::events::{GameEvent, GameEvents};
 use crate::ghost_state::GhostColor;
 use crate::location::Direction::*;
 use crate::{
@@ -11,13 +12,26 @@ use crate::{
 
 pub type Position = (i8, i8);
 
+// How a ghost's next move was planned
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum GhostPlan {
+    // The ghost was not planned, so it stays where it is
+    Skipped,
+    // The ghost was trapped, so it turned around
+    Reversed,
+    // The ghost moved towards its target
+    Targeted,
+    // The ghost was frightened, so it moved at random
+    Random,
+}
+
 /***************************** Bitwise Operations *****************************/
 
-fn get_bit_u32(num: u32, bit_idx: usize) -> bool {
+pub(crate) fn get_bit_u32(num: u32, bit_idx: usize) -> bool {
     ((num >> bit_idx) & 1) == 1
 }
 
-fn modify_bit_u32(num: &mut u32, bit_idx: usize, bit_val: bool) {
+pub(crate) fn modify_bit_u32(num: &mut u32, bit_idx: usize, bit_val: bool) {
     // If the bit is true, we should set the bit, otherwise we clear it
     if bit_val {
         *num |= 1 << bit_idx;
@@ -59,13 +73,16 @@ impl GameState {
     /*
     Collects a pellet if it is at a given location
     */
-    pub fn collect_pellet(&mut self, pos: Position) {
+    pub fn collect_pellet(&mut self, pos: Position, events: &mut GameEvents) {
         let (row, col) = pos;
 
         // Collect fruit, if applicable
         if self.fruit_exists() && self.pacman_loc.collides_with(self.fruit_loc) {
             self.set_fruit_steps(0);
-            self.increment_score(FRUIT_POINTS);
+            self.increment_score(self.config.fruit_points);
+            events.push(GameEvent::FruitEaten {
+                points: self.config.fruit_points,
+            });
         }
 
         // If there's no pellet, return
@@ -77,8 +94,8 @@ impl GameState {
         modify_bit_u32(&mut self.pellets[row as usize], col as usize, false);
         self.decrement_num_pellets();
 
-        // If the we are in particular rows and columns, it is a super pellet
-        let super_pellet = is_super_pellet((row, col));
+        // If the maze has a super pellet here, it is a super pellet
+        let super_pellet = self.config.maze.super_pellet_at((row, col));
 
         // Make all the ghosts frightened if a super pellet is collected
         if super_pellet {
@@ -87,9 +104,11 @@ impl GameState {
 
         // Update the score, depending on the pellet type
         if super_pellet {
-            self.increment_score(SUPER_PELLET_POINTS);
+            self.increment_score(self.config.super_pellet_points);
+            events.push(GameEvent::SuperPelletEaten { pos });
         } else {
-            self.increment_score(PELLET_POINTS);
+            self.increment_score(self.config.pellet_points);
+            events.push(GameEvent::PelletEaten { pos });
         }
 
         // Act depending on the number of pellets left over
@@ -97,19 +116,22 @@ impl GameState {
 
         // Spawn fruit, if applicable
         if !self.fruit_exists()
-            && (num_pellets == FRUIT_THRESHOLD1 || num_pellets == FRUIT_THRESHOLD2)
+            && (num_pellets == self.config.fruit_threshold1
+                || num_pellets == self.config.fruit_threshold2)
         {
-            self.set_fruit_steps(FRUIT_DURATION);
+            self.set_fruit_steps(self.config.fruit_duration);
+            events.push(GameEvent::FruitSpawned);
         }
 
         // Other pellet-related events
-        if num_pellets == ANGER_THRESHOLD1 || num_pellets == ANGER_THRESHOLD2 {
+        if num_pellets == self.config.anger_threshold1
+            || num_pellets == self.config.anger_threshold2
+        {
             // Ghosts get angry (speeding up)
             self.set_update_period(u8::max(1, self.get_update_period().saturating_sub(2)));
-            self.mode = GameMode::CHASE;
-            self.set_mode_steps(GameMode::CHASE.duration());
+            self.switch_mode(GameMode::CHASE, events);
         } else if num_pellets == 0 {
-            self.level_reset();
+            self.level_reset(events);
             self.increment_level();
         }
     }
@@ -122,21 +144,20 @@ impl GameState {
 
         // Returns the bit of the wall row corresponding to the column
         let (row, col) = pos;
-        get_bit_u32(self.walls[row as usize], col as usize)
+        get_bit_u32(self.config.maze.walls[row as usize], col as usize)
     }
 
     // Determines if the ghost house is at a given location
     pub fn ghost_spawn_at(&self, pos: Position) -> bool {
-        let (row, col) = pos;
-        (13..=14).contains(&row) && (11..=15).contains(&col)
+        self.config.maze.ghost_house_at(pos)
     }
 
     /***************************** Collision Handling *****************************/
 
     // Check collisions between Pacman and all the ghosts, and respawn ghosts/Pacman as necessary.
-    pub fn check_collisions(&mut self) {
+    pub fn check_collisions(&mut self, events: &mut GameEvents) {
         // Loop over all the ghosts and check for collisions with Pacman.
-        let mut num_ghosts_eaten = 0;
+        let mut ghosts_eaten = [None; 4];
         let mut did_pacman_die = false;
         let pacman_loc = self.pacman_loc;
         for ghost in &mut self.ghosts {
@@ -149,9 +170,9 @@ impl GameState {
                 // If the ghost is frightened, Pacman eats it, otherwise Pacman dies.
                 if ghost.is_frightened() {
                     // Respawn the ghost.
-                    ghost.respawn();
+                    ghost.respawn(&self.config);
 
-                    num_ghosts_eaten += 1;
+                    ghosts_eaten[ghost.color as usize] = Some(ghost.color);
                 } else {
                     did_pacman_die = true;
                     break;
@@ -160,11 +181,13 @@ impl GameState {
         }
 
         if did_pacman_die {
-            self.death_reset();
+            self.death_reset(events);
         } else {
-            for _ in 0..num_ghosts_eaten {
+            for color in ghosts_eaten.into_iter().flatten() {
                 // Add points corresponding to the current combo length.
-                self.increment_score(COMBO_MULTIPLIER << self.ghost_combo);
+                let points = self.config.combo_multiplier << self.ghost_combo;
+                self.increment_score(points);
+                events.push(GameEvent::GhostEaten { color, points });
 
                 // Increment the ghost respawn combo.
                 self.ghost_combo += 1;
@@ -175,20 +198,22 @@ impl GameState {
     /***************************** Event-Based Resets *****************************/
 
     // Reset the board (while leaving pellets alone) after Pacman dies
-    fn death_reset(&mut self) {
+    fn death_reset(&mut self, events: &mut GameEvents) {
         // Set Pacman to be in an empty state
         self.pacman_loc = EMPTY_LOC;
 
         // Decrease the number of lives Pacman has left
         self.decrement_lives();
+        events.push(GameEvent::PacmanDied {
+            lives_left: self.get_lives(),
+        });
 
         /*
             If the mode is not the initial mode and the ghosts aren't angry,
             change the mode back to the initial mode
         */
-        if self.get_num_pellets() > ANGER_THRESHOLD1 {
-            self.mode = INIT_MODE;
-            self.set_mode_steps(INIT_MODE.duration());
+        if self.get_num_pellets() > self.config.anger_threshold1 {
+            self.switch_mode(self.config.init_mode, events);
         }
 
         // Set the fruit steps back to 0
@@ -199,16 +224,19 @@ impl GameState {
     }
 
     // Reset the board (including pellets) after Pacman clears a level
-    fn level_reset(&mut self) {
+    fn level_reset(&mut self, events: &mut GameEvents) {
+        events.push(GameEvent::LevelCleared {
+            level: self.get_level(),
+        });
+
         // Set Pacman to be in an empty state
         self.pacman_loc = EMPTY_LOC;
 
         // If the mode is not the initial mode, change it
-        self.mode = INIT_MODE;
-        self.set_mode_steps(INIT_MODE.duration());
+        self.switch_mode(self.config.init_mode, events);
 
         // Reset the level penalty
-        self.set_level_steps(LEVEL_DURATION);
+        self.set_level_steps(self.config.level_duration);
 
         // Set the fruit steps back to 0
         self.set_fruit_steps(0);
@@ -220,12 +248,23 @@ impl GameState {
         self.reset_pellets();
     }
 
+    // Switch to a given mode for its full duration, reporting it if the mode changed
+    pub(crate) fn switch_mode(&mut self, mode: GameMode, events: &mut GameEvents) {
+        if self.mode != mode {
+            events.push(GameEvent::ModeChanged { mode });
+        }
+        self.mode = mode;
+        self.set_mode_steps(self.config.mode_duration(mode));
+    }
+
     /************************** Motion (Pacman Location) **************************/
 
-    // Move Pacman one space in a given direction
-    pub fn move_pacman_dir(&mut self, dir: Direction) {
+    /// Move Pacman one space in a given direction, returning the events that happened
+    pub fn move_pacman_dir(&mut self, dir: Direction) -> GameEvents {
+        let mut events = GameEvents::new();
+
         // Check collisions with all the ghosts
-        self.check_collisions();
+        self.check_collisions(&mut events);
 
         // Calculate the next row and column
         let next_loc = self.pacman_loc.get_neighbor_coords(dir);
@@ -235,19 +274,20 @@ impl GameState {
 
         // Check if there is a wall at the anticipated location, and return if so
         if self.wall_at(next_loc) {
-            return;
+            return events;
         }
 
         // Move Pacman the anticipated spot
         self.pacman_loc.update_coords(next_loc);
-        self.collect_pellet(next_loc);
+        self.collect_pellet(next_loc, &mut events);
+        events
     }
 
     // Move Pacman back to its spawn point, if necessary
     pub fn try_respawn_pacman(&mut self) {
         // Set Pacman to be in its original state
         if self.pacman_loc.is_empty() && self.get_lives() > 0 {
-            self.pacman_loc = PACMAN_SPAWN_LOC;
+            self.pacman_loc = self.config.maze.pacman_spawn_loc;
         }
     }
 
@@ -264,7 +304,7 @@ impl GameState {
                 To frighten a ghost, set its fright steps to a specified value
                 and trap it for one step (to force the direction to reverse)
             */
-            ghost.set_fright_steps(GHOST_FRIGHT_STEPS);
+            ghost.set_fright_steps(self.config.ghost_fright_steps);
             if !ghost.is_trapped() {
                 ghost.set_trapped_steps(1);
             }
@@ -292,7 +332,7 @@ impl GameState {
 
         // Reset each of the ghosts
         for ghost in &mut self.ghosts {
-            ghost.reset();
+            ghost.reset(&self.config);
         }
 
         // If no lives are left, set all ghosts to stare at the player, menacingly
@@ -312,12 +352,19 @@ impl GameState {
     pub fn update_all_ghosts(&mut self) {
         // Loop over the individual ghosts
         for ghost in &mut self.ghosts {
-            ghost.update();
+            ghost.update(&self.config);
         }
     }
 
     // A game state function to plan all ghosts at once
     pub fn plan_all_ghosts(&mut self) {
+        self.plan_ghosts();
+    }
+
+    // Plan all ghosts at once, returning how each ghost's next move was planned
+    pub(crate) fn plan_ghosts(&mut self) -> [GhostPlan; 4] {
+        let mut plans = [GhostPlan::Skipped; 4];
+
         // Plan each ghost's next move
         for ghost_idx in 0..self.ghosts.len() {
             let chase_color = self.ghosts[ghost_idx].color;
@@ -325,7 +372,7 @@ impl GameState {
 
             // If the location is empty (i.e. after a reset/respawn), don't plan
             if self.ghosts[ghost_idx].loc.is_empty() {
-                return;
+                return plans;
             }
 
             // Determine the next position based on the current direction
@@ -337,7 +384,8 @@ impl GameState {
                 self.ghosts[ghost_idx].next_loc.dir =
                     self.ghosts[ghost_idx].next_loc.dir.opposite();
                 self.ghosts[ghost_idx].dec_trapped_steps();
-                return;
+                plans[ghost_idx] = GhostPlan::Reversed;
+                return plans;
             }
 
             // Decide on a target for this ghost, depending on the game mode.
@@ -347,15 +395,12 @@ impl GameState {
 
                 Otherwise: pick chase or scatter targets, depending on the mode.
             */
+            let red_spawn_loc = self.config.maze.ghost_spawn_locs[Red as usize];
             let target_loc = if self.ghosts[ghost_idx].spawning
-                && !self.ghosts[ghost_idx]
-                    .loc
-                    .collides_with(GHOST_SPAWN_LOCS[Red as usize])
-                && !self.ghosts[ghost_idx]
-                    .next_loc
-                    .collides_with(GHOST_SPAWN_LOCS[Red as usize])
+                && !self.ghosts[ghost_idx].loc.collides_with(red_spawn_loc)
+                && !self.ghosts[ghost_idx].next_loc.collides_with(red_spawn_loc)
             {
-                GHOST_SPAWN_LOCS[Red as usize].get_coords()
+                red_spawn_loc.get_coords()
             } else {
                 match self.mode {
                     GameMode::CHASE => chase_target,
@@ -385,7 +430,7 @@ impl GameState {
 
                     // Determine if the move would help the ghost escape the ghost house,
                     // and make it a valid one if so.
-                    if loc == GHOST_HOUSE_EXIT_POS {
+                    if loc == self.config.maze.ghost_house_exit_pos {
                         return true;
                     }
                 }
@@ -400,9 +445,11 @@ impl GameState {
                 let mut rng = SmallRng::seed_from_u64(self.seed);
                 let chosen_move = valid_moves.choose(&mut rng);
                 self.seed = rng.gen();
+                plans[ghost_idx] = GhostPlan::Random;
                 chosen_move
             } else {
                 // Otherwise, pick the move that takes the ghost closest to its target.
+                plans[ghost_idx] = GhostPlan::Targeted;
                 valid_moves.min_by_key(|&(_dir, loc)| dist_sq(loc, target_loc))
             }
             .expect("ghost has no valid moves!");
@@ -410,6 +457,7 @@ impl GameState {
             // Once we have picked a move, set next_loc.dir to that direction.
             self.ghosts[ghost_idx].next_loc.dir = chosen_move.0;
         }
+        plans
     }
 
     /************************ Ghost Targeting (Chase Mode) ************************/
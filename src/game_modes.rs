@@ -11,7 +11,7 @@ pub enum GameMode {
 
 impl GameMode {
     /// Returns the length of the game mode, in units of steps (update periods).
-    pub fn duration(self) -> u8 {
+    pub const fn duration(self) -> u8 {
         match self {
             GameMode::SCATTER => 60, // 30 seconds at 24 fps
             GameMode::CHASE => 180,  // 90 seconds at 24 fps
@@ -1,24 +1,100 @@
 use array_init::array_init;
+use core::fmt;
 use core2::io;
-use core2::io::{Cursor, Read};
 #[cfg(feature = "std")]
 use rand::{prelude::SmallRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-#[cfg(feature = "std")]
-use std::collections::{HashMap, VecDeque};
 
-#[cfg(feature = "std")]
+use crate::codec::{ByteReader, ByteWriter, DecodeError, DecodeMode, PACKET_LEN};
+use crate::config::GameConfig;
+use crate::events::GameEvents;
 use crate::game_helpers::Position;
 use crate::ghost_state::{GhostColor, GHOST_NAMES};
-#[cfg(feature = "std")]
 use crate::location::Direction;
+use crate::logging::game_log;
 use crate::{game_modes::GameMode, ghost_state::GhostState, location::LocationState, variables::*};
 
+/// The outcome of [`GameState::step_with_action`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StepResult {
+    /// The events that happened, from both the move and the ticks
+    pub events: GameEvents,
+    /// Whether Pacman has run out of lives
+    pub game_over: bool,
+    /// The number of ticks that the engine advanced
+    pub ticks: u32,
+}
+
+/// How Pacman gets to a location given to [`GameState::set_pacman_location_with`].
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LocationPolicy {
+    /// Move Pacman along a shortest path, collecting pellets and the fruit and colliding
+    /// with ghosts along the way, as if it had moved there itself
+    #[default]
+    ReplayPath,
+    /// Move Pacman straight to the location, only collecting what is there
+    Teleport,
+    /// Like [`LocationPolicy::ReplayPath`], but reject locations that are more than this
+    /// many moves away, such as those from glitches in localization
+    MaxDistance(u16),
+}
+
+/// An error from setting Pacman's location, which leaves the game state unchanged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocationError {
+    /// The location is not on the maze
+    OutOfBounds { location: Position },
+    /// The location is a wall
+    Wall { location: Position },
+    /// There is no path from Pacman to the location
+    Unreachable { location: Position },
+    /// The location is further away than the policy allows
+    TooFar {
+        location: Position,
+        distance: u16,
+        max_distance: u16,
+    },
+    /// Pacman is dead, and can't move until it respawns
+    Dead { location: Position },
+}
+
+impl fmt::Display for LocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationError::OutOfBounds { location } => {
+                write!(f, "location {location:?} is out of bounds")
+            }
+            LocationError::Wall { location } => write!(f, "location {location:?} is a wall"),
+            LocationError::Unreachable { location } => {
+                write!(f, "no path to location {location:?}")
+            }
+            LocationError::TooFar {
+                location,
+                distance,
+                max_distance,
+            } => write!(
+                f,
+                "location {location:?} is {distance} moves away, more than {max_distance}"
+            ),
+            LocationError::Dead { location } => {
+                write!(f, "Pacman is dead, so it can't move to {location:?}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LocationError {}
+
 /// A game state object, to hold the internal game state and provide
 /// helper methods that can be accessed by the game engine.
 ///
-/// Logging can be disabled by setting the environment variable DISABLE_PACMAN_LOGGING
-#[derive(Clone, Debug, Serialize, Deserialize, PartialOrd, PartialEq)]
+/// Changes to the update period, level and lives are logged through the `log` crate
+/// when the `log` feature is enabled.
+///
+/// The maze is shared through the configuration rather than stored in each state, so
+/// game states are small and can be copied cheaply, for example during tree search.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, PartialEq)]
 pub struct GameState {
     /* Message header - 4 bytes */
     /// Current ticks.
@@ -73,11 +149,11 @@ pub struct GameState {
     pub num_pellets: u16,
 
     /* Auxiliary (non-serialized) state information */
-    /// Wall state
-    pub walls: [u32; MAZE_ROWS],
-
     /// Used to generate ghost moves (updated each time)
     pub seed: u64,
+
+    /// The rules that this game is played with
+    pub config: GameConfig,
 }
 
 #[cfg(feature = "std")]
@@ -96,104 +172,154 @@ impl GameState {
 
     /// Creates a new game state with default values.
     pub fn new_with_seed(seed: u64) -> Self {
+        Self::new_with_config(seed, GameConfig::default())
+    }
+
+    /// Creates a new game state that is played with the given rules.
+    pub fn new_with_config(seed: u64, config: GameConfig) -> Self {
         Self {
             // Message header
             curr_ticks: 0,
-            update_period: INIT_UPDATE_PERIOD,
-            mode: INIT_MODE,
+            update_period: config.init_update_period,
+            mode: config.init_mode,
             paused: true,
 
             // Additional header-related info
-            mode_steps: INIT_MODE.duration(),
-            level_steps: LEVEL_DURATION,
+            mode_steps: config.mode_duration(config.init_mode),
+            level_steps: config.level_duration,
 
             // Game info
             curr_score: 0,
-            curr_level: INIT_LEVEL,
-            curr_lives: INIT_LIVES,
+            curr_level: config.init_level,
+            curr_lives: config.init_lives,
 
-            pacman_loc: PACMAN_SPAWN_LOC,
+            pacman_loc: config.maze.pacman_spawn_loc,
 
             // Fruit
-            fruit_loc: FRUIT_SPAWN_LOC,
+            fruit_loc: config.maze.fruit_spawn_loc,
             fruit_steps: 0,
 
             // Ghosts
-            ghosts: array_init(|color| GhostState::new((color as u8).try_into().unwrap())),
+            ghosts: array_init(|color| GhostState::new((color as u8).try_into().unwrap(), &config)),
             ghost_combo: 0,
 
             // Pellet count at the start
-            pellets: INIT_PELLETS,
-            num_pellets: INIT_PELLET_COUNT,
-
-            // Walls
-            walls: INIT_WALLS,
+            pellets: config.maze.pellets,
+            num_pellets: config.maze.num_pellets,
 
             // For ghost moves
             seed,
+
+            config,
         }
     }
 
-    pub fn from_bytes(bytes: &[u8], seed: u64) -> io::Result<Self> {
-        let mut cursor = Cursor::new(bytes);
-
-        fn get_u8(cursor: &mut Cursor<&[u8]>) -> io::Result<u8> {
-            let mut buf = [0];
-            cursor.read_exact(&mut buf)?;
-            Ok(buf[0])
-        }
+    /// Decodes a game state from the server, ignoring any trailing bytes.
+    pub fn from_bytes(bytes: &[u8], seed: u64) -> Result<Self, DecodeError> {
+        Self::from_bytes_with_config(bytes, seed, GameConfig::default(), DecodeMode::Lenient)
+    }
 
-        fn get_u16(cursor: &mut Cursor<&[u8]>) -> io::Result<u16> {
-            let mut buf = [0; 2];
-            cursor.read_exact(&mut buf)?;
-            Ok(u16::from_be_bytes(buf))
-        }
+    /// Decodes a game state from the server, rejecting input that is not exactly
+    /// [`PACKET_LEN`] bytes long.
+    pub fn from_bytes_strict(bytes: &[u8], seed: u64) -> Result<Self, DecodeError> {
+        Self::from_bytes_with_config(bytes, seed, GameConfig::default(), DecodeMode::Strict)
+    }
 
-        fn get_u32(cursor: &mut Cursor<&[u8]>) -> io::Result<u32> {
-            let mut buf = [0; 4];
-            cursor.read_exact(&mut buf)?;
-            Ok(u32::from_be_bytes(buf))
-        }
+    /// Decodes a game state from the server, to be played with the given rules.
+    pub fn from_bytes_with_config(
+        bytes: &[u8],
+        seed: u64,
+        config: GameConfig,
+        decode_mode: DecodeMode,
+    ) -> Result<Self, DecodeError> {
+        const GHOST_LOC_FIELDS: [&str; 4] = [
+            "red location",
+            "pink location",
+            "cyan location",
+            "orange location",
+        ];
+        const GHOST_AUX_FIELDS: [&str; 4] = ["red aux", "pink aux", "cyan aux", "orange aux"];
+        const GHOST_AUX2_FIELDS: [&str; 4] = ["red aux2", "pink aux2", "cyan aux2", "orange aux2"];
+
+        decode_mode.check_len(bytes, PACKET_LEN)?;
+        let mut r = ByteReader::new(bytes);
 
         // General game info
-        let curr_ticks = get_u16(&mut cursor)? as u32;
-        let update_period = get_u8(&mut cursor)?;
-        let (mode, paused) = match get_u8(&mut cursor)? {
+        let curr_ticks = r.u16("curr_ticks")? as u32;
+        let offset = r.offset();
+        let update_period = match r.u8("update_period")? {
+            0 => {
+                return Err(DecodeError::InvalidValue {
+                    field: "update_period",
+                    offset,
+                    value: 0,
+                })
+            }
+            period => period,
+        };
+        let offset = r.offset();
+        let (mode, paused) = match r.u8("mode")? {
             0 => (GameMode::CHASE, true),
             1 => (GameMode::SCATTER, false),
             2 => (GameMode::CHASE, false),
-            _ => unreachable!(),
+            value => {
+                return Err(DecodeError::InvalidValue {
+                    field: "mode",
+                    offset,
+                    value,
+                })
+            }
         };
-        let mode_steps = get_u8(&mut cursor)?;
-        let _mode_duration = get_u8(&mut cursor)?;
-        let level_steps = get_u16(&mut cursor)?;
-        let curr_score = get_u16(&mut cursor)?;
-        let curr_level = get_u8(&mut cursor)?;
-        let curr_lives = get_u8(&mut cursor)?;
-        let ghost_combo = get_u8(&mut cursor)?;
+        let mode_steps = r.u8("mode_steps")?;
+        let _mode_duration = r.u8("mode_duration")?;
+        let level_steps = r.u16("level_steps")?;
+        let curr_score = r.u16("curr_score")?;
+        let curr_level = r.u8("curr_level")?;
+        let curr_lives = r.u8("curr_lives")?;
+        let ghost_combo = r.u8("ghost_combo")?;
 
         // ghost info
-        let mut ghosts = [GhostState::from_bytes(GhostColor::Red, EMPTY_LOC, 0, 0); 4];
+        let mut ghosts = [GhostState::from_bytes(GhostColor::Red, EMPTY_LOC, 0, 0, &config); 4];
         for (i, g) in ghosts.iter_mut().enumerate() {
-            let loc = LocationState::from_bytes([get_u8(&mut cursor)?, get_u8(&mut cursor)?])?;
+            let offset = r.offset();
             *g = GhostState::from_bytes(
                 GHOST_NAMES[i],
-                loc,
-                get_u8(&mut cursor)?,
-                get_u8(&mut cursor)?,
-            )
+                r.location(GHOST_LOC_FIELDS[i])?,
+                r.u8(GHOST_AUX_FIELDS[i])?,
+                r.u8(GHOST_AUX2_FIELDS[i])?,
+                &config,
+            );
+
+            // The ghost must be somewhere that it can move to, and so must the cell that it
+            // faces, since ghosts can't turn back
+            let open = |pos| config.maze.open_to_ghost(pos, g.spawning);
+            r.check_open(GHOST_LOC_FIELDS[i], offset, g.loc.get_coords(), open)?;
+            if !g.loc.is_empty() && !open(g.loc.get_ahead_coords(1)) {
+                return Err(DecodeError::InvalidLocation {
+                    field: GHOST_LOC_FIELDS[i],
+                    offset,
+                    bytes: g.loc.to_bytes(),
+                });
+            }
         }
 
-        // pacman location info
-        let pacman_loc = LocationState::from_bytes([get_u8(&mut cursor)?, get_u8(&mut cursor)?])?;
+        // pacman location info, which must be empty or on a walkable cell
+        let offset = r.offset();
+        let pacman_loc = r.location("pacman location")?;
+        r.check_open("pacman location", offset, pacman_loc.get_coords(), |pos| {
+            !config.maze.wall_at(pos)
+        })?;
 
         // fruit location info
-        let fruit_loc = LocationState::from_bytes([get_u8(&mut cursor)?, get_u8(&mut cursor)?])?;
-        let fruit_steps = get_u8(&mut cursor)?;
-        let _fruit_duration = get_u8(&mut cursor)?;
+        let fruit_loc = r.location("fruit location")?;
+        let fruit_steps = r.u8("fruit_steps")?;
+        let _fruit_duration = r.u8("fruit_duration")?;
 
         // Pellet info
-        let pellets = array_init(|_| get_u32(&mut cursor).unwrap_or(0));
+        let mut pellets = [0; MAZE_ROWS];
+        for row in &mut pellets {
+            *row = r.u32("pellets")?;
+        }
 
         let mut s = Self {
             curr_ticks,
@@ -212,72 +338,87 @@ impl GameState {
             ghost_combo,
             num_pellets: pellets.iter().map(|x| x.count_ones()).sum::<u32>() as u16,
             pellets,
-            walls: INIT_WALLS,
             seed,
+            config,
         };
         s.plan_all_ghosts();
         Ok(s)
     }
 
+    /// Encodes the game state in the same format as the server.
     #[cfg(feature = "std")]
     pub fn to_bytes(&self) -> Vec<u8> {
-        const FRUIT_DURATION: u8 = 30;
+        self.to_packet().to_vec()
+    }
 
-        let mut b = vec![];
+    /// Encodes the game state in the same format as the server, without allocating.
+    pub fn to_packet(&self) -> [u8; PACKET_LEN] {
+        let mut buf = [0; PACKET_LEN];
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    /// Writes the game state, in the same format as the server, to a writer.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_packet())
+    }
+
+    /// Encodes the game state in the same format as the server into a buffer.
+    pub fn encode_into(&self, buf: &mut [u8; PACKET_LEN]) {
+        let mut w = ByteWriter::new(buf);
 
-        b.append(&mut (self.curr_ticks as u16).to_be_bytes().to_vec());
-        b.push(self.update_period);
-        b.push(match (self.mode, self.paused) {
+        w.u16(self.curr_ticks as u16);
+        w.u8(self.update_period);
+        w.u8(match (self.mode, self.paused) {
             (_, true) => 0,
             (GameMode::SCATTER, false) => 1,
             (GameMode::CHASE, false) => 2,
         });
-        b.push(self.mode_steps);
+        w.u8(self.mode_steps);
         // mode_duration
-        b.push(match (self.mode, self.paused) {
-            (GameMode::SCATTER, false) => GameMode::SCATTER.duration(),
-            (GameMode::CHASE, false) => GameMode::CHASE.duration(),
-            _ => 255,
+        w.u8(match self.paused {
+            false => self.config.mode_duration(self.mode),
+            true => 255,
         });
-        b.append(&mut self.level_steps.to_be_bytes().to_vec());
-        b.append(&mut self.curr_score.to_be_bytes().to_vec());
-        b.push(self.curr_level);
-        b.push(self.curr_lives);
-        b.push(self.ghost_combo);
+        w.u16(self.level_steps);
+        w.u16(self.curr_score);
+        w.u8(self.curr_level);
+        w.u8(self.curr_lives);
+        w.u8(self.ghost_combo);
 
         // ghost info
-        for g in 0..4 {
-            let ghost = &self.ghosts[g];
-            b.append(&mut ghost.loc.to_bytes().to_vec());
-            b.push(ghost.get_aux());
-            b.push(ghost.get_aux2());
+        for ghost in &self.ghosts {
+            w.bytes(&ghost.loc.to_bytes());
+            w.u8(ghost.get_aux());
+            w.u8(ghost.get_aux2());
         }
 
         // pacman location
-        b.append(&mut self.pacman_loc.to_bytes().to_vec());
+        w.bytes(&self.pacman_loc.to_bytes());
 
         // fruit location info
-        b.append(&mut self.fruit_loc.to_bytes().to_vec());
-        b.push(self.fruit_steps);
-        b.push(FRUIT_DURATION);
+        w.bytes(&self.fruit_loc.to_bytes());
+        w.u8(self.fruit_steps);
+        w.u8(self.config.fruit_duration);
 
         // pellet info
-        for i in 0..31 {
-            b.append(&mut self.pellets[i].to_be_bytes().to_vec());
+        for row in self.pellets {
+            w.u32(row);
         }
 
-        b
+        debug_assert_eq!(w.offset(), PACKET_LEN);
     }
 
-    /// Start the game engine
-    pub fn step(&mut self) {
+    /// Advance the game engine by one tick, returning the events that happened
+    pub fn step(&mut self) -> GameEvents {
+        let mut events = GameEvents::new();
         let lives_before = self.curr_lives;
         self.next_tick();
         if self.update_ready() {
             self.update_all_ghosts();
             self.try_respawn_pacman();
-            self.check_collisions();
-            self.handle_step_events();
+            self.check_collisions(&mut events);
+            self.handle_step_events(&mut events);
         }
         if self.update_ready() {
             self.plan_all_ghosts();
@@ -285,72 +426,97 @@ impl GameState {
         if self.curr_lives < lives_before {
             self.paused = true;
         }
+        events
     }
 
-    #[cfg(feature = "std")]
-    /// Set pacman's location
-    pub fn set_pacman_location(&mut self, location: Position) {
-        // Check if there is a wall at the anticipated location, and return if so
-        if !self.in_bounds(location) || self.wall_at(location) {
-            eprintln!("set_pacman_location out of bounds at {location:?}");
-            return;
+    /// Move Pacman in the given direction, then advance the engine to the next update,
+    /// the same way the server does when Pacman moves once per update period.
+    ///
+    /// Like [`GameState::step`], this ignores whether the game is paused. Nothing happens
+    /// once the game is over.
+    pub fn step_with_action(&mut self, dir: Direction) -> StepResult {
+        let mut result = StepResult {
+            events: GameEvents::new(),
+            game_over: self.is_game_over(),
+            ticks: 0,
+        };
+        if result.game_over {
+            return result;
         }
-        let likely_path = self.bfs((self.pacman_loc.row, self.pacman_loc.col), location);
-        if let Some(likely_path) = likely_path {
-            for LocationState { dir, .. } in likely_path {
-                self.move_pacman_dir(dir);
+
+        result.events = self.move_pacman_dir(dir);
+        while !self.is_game_over() {
+            result.events.extend(&self.step());
+            result.ticks += 1;
+            if self.update_ready() {
+                break;
             }
-            self.collect_pellet(location);
-            self.check_collisions();
-        } else {
-            eprintln!("Could not find path to position!");
         }
+        result.game_over = self.is_game_over();
+        result
     }
 
-    #[cfg(feature = "std")]
-    pub fn bfs(&self, start: Position, end: Position) -> Option<Vec<LocationState>> {
-        if start == end {
-            return Some(vec![]);
+    /// Set pacman's location by moving it along a shortest path, returning the events
+    /// that happened along the way (see [`LocationPolicy::ReplayPath`])
+    ///
+    /// Long paths may produce more events than [`GameEvents`] can hold.
+    pub fn set_pacman_location(&mut self, location: Position) -> Result<GameEvents, LocationError> {
+        self.set_pacman_location_with(location, LocationPolicy::ReplayPath)
+    }
+
+    /// Set pacman's location, getting there as the policy says, and return the events
+    /// that happened
+    pub fn set_pacman_location_with(
+        &mut self,
+        location: Position,
+        policy: LocationPolicy,
+    ) -> Result<GameEvents, LocationError> {
+        let mut events = GameEvents::new();
+
+        // Check that the location is on the maze and not a wall
+        if !self.in_bounds(location) {
+            return Err(LocationError::OutOfBounds { location });
         }
-        let mut visited: HashMap<Position, Option<(Position, Direction)>> = HashMap::new();
-        let mut queue: VecDeque<Position> = VecDeque::new();
-        queue.push_back(start);
-        visited.insert(start, None);
-        while let Some(pos) = queue.pop_front() {
-            let loc = LocationState {
-                row: pos.0,
-                col: pos.1,
-                dir: Direction::Stay,
-            };
-            for dir in Direction::all_except_stay() {
-                let next = loc.get_neighbor_coords(dir);
-                if !self.wall_at(next) && !visited.contains_key(&next) {
-                    if next == end {
-                        let mut path = vec![LocationState {
-                            row: pos.0,
-                            col: pos.1,
-                            dir,
-                        }];
-                        let mut node = pos;
-                        while let Some(Some((previous, dir))) = visited.remove(&node) {
-                            node = previous;
-                            path.insert(
-                                0,
-                                LocationState {
-                                    row: previous.0,
-                                    col: previous.1,
-                                    dir,
-                                },
-                            );
-                        }
-                        return Some(path);
-                    }
-                    visited.insert(next, Some((pos, dir)));
-                    queue.push_back(next);
+        if self.wall_at(location) {
+            return Err(LocationError::Wall { location });
+        }
+
+        // Pacman isn't on the maze while it waits to respawn
+        if self.pacman_loc.is_empty() {
+            return Err(LocationError::Dead { location });
+        }
+
+        if policy == LocationPolicy::Teleport {
+            self.pacman_loc.update_coords(location);
+        } else {
+            let likely_path = self
+                .find_path((self.pacman_loc.row, self.pacman_loc.col), location)
+                .ok_or(LocationError::Unreachable { location })?;
+            if let LocationPolicy::MaxDistance(max_distance) = policy {
+                let distance = likely_path.len() as u16;
+                if distance > max_distance {
+                    return Err(LocationError::TooFar {
+                        location,
+                        distance,
+                        max_distance,
+                    });
                 }
             }
+            for dir in likely_path.directions() {
+                events.extend(&self.move_pacman_dir(dir));
+            }
         }
-        None
+        self.collect_pellet(location, &mut events);
+        self.check_collisions(&mut events);
+        Ok(events)
+    }
+
+    /// Finds a shortest path for Pacman between two cells, as the location and direction
+    /// of each move (see [`GameState::find_path`] for a version that does not allocate).
+    #[cfg(feature = "std")]
+    pub fn bfs(&self, start: Position, end: Position) -> Option<Vec<LocationState>> {
+        self.find_path(start, end)
+            .map(|path| path.steps().collect())
     }
 
     /**************************** Curr Ticks Functions ****************************/
@@ -369,16 +535,14 @@ impl GameState {
 
     /// Helper function to set the update period
     pub fn set_update_period(&mut self, period: u8) {
-        // Send a message to the terminal
-        #[cfg(feature = "std")]
-        if std::env::var("DISABLE_PACMAN_LOGGING").is_ok() {
-            println!(
-                "\x1b[36mGAME: Update period changed ({} -> {}) (t = {})\x1b[0m\n",
-                self.get_update_period(),
-                period,
-                self.curr_ticks,
-            );
-        }
+        // Send a message to the log
+        game_log!(
+            info,
+            tick = self.curr_ticks,
+            old = self.get_update_period(),
+            new = period;
+            "Update period changed"
+        );
 
         self.update_period = period // Update the update period
     }
@@ -411,7 +575,7 @@ impl GameState {
         self.curr_level = level; // Update the level
 
         // Adjust the initial update period accordingly
-        let suggested_period = (INIT_UPDATE_PERIOD as i32) - 2 * ((level as i32) - 1);
+        let suggested_period = (self.config.init_update_period as i32) - 2 * ((level as i32) - 1);
         self.set_update_period(i32::max(1, suggested_period) as u8);
     }
 
@@ -425,16 +589,14 @@ impl GameState {
             return;
         }
 
-        // Send a message to the terminal
-        #[cfg(feature = "std")]
-        if std::env::var("DISABLE_PACMAN_LOGGING").is_ok() {
-            println!(
-                "\x1b[32mGAME: Next level ({} -> {}) (t = {})\x1b[0m\n",
-                level,
-                level + 1,
-                self.curr_ticks,
-            );
-        }
+        // Send a message to the log
+        game_log!(
+            info,
+            tick = self.curr_ticks,
+            old = level,
+            new = level + 1;
+            "Next level"
+        );
 
         self.set_level(self.curr_level + 1); // Update the level
     }
@@ -448,19 +610,23 @@ impl GameState {
 
     /// Helper function to set the lives left
     pub fn set_lives(&mut self, lives: u8) {
-        // Send a message to the terminal
-        #[cfg(feature = "std")]
-        if std::env::var("DISABLE_PACMAN_LOGGING").is_ok() {
-            println!(
-                "\x1b[36mGAME: Lives changed ({} -> {})\x1b[0m\n",
-                self.get_lives(),
-                lives,
-            );
-        }
+        // Send a message to the log
+        game_log!(
+            info,
+            tick = self.curr_ticks,
+            old = self.get_lives(),
+            new = lives;
+            "Lives changed"
+        );
 
         self.curr_lives = lives; // Update the lives
     }
 
+    /// Whether Pacman has run out of lives
+    pub fn is_game_over(&self) -> bool {
+        self.curr_lives == 0
+    }
+
     /// Helper function to decrement the lives left
     pub fn decrement_lives(&mut self) {
         // Keep track of how many lives Pacman has left
@@ -471,16 +637,14 @@ impl GameState {
             return;
         }
 
-        // Send a message to the terminal
-        #[cfg(feature = "std")]
-        if std::env::var("DISABLE_PACMAN_LOGGING").is_ok() {
-            println!(
-                "\x1b[31mGAME: Pacman lost a life ({} -> {}) (t = {})\x1b[0m\n",
-                lives,
-                lives - 1,
-                self.curr_ticks,
-            );
-        }
+        // Send a message to the log
+        game_log!(
+            info,
+            tick = self.curr_ticks,
+            old = lives,
+            new = lives - 1;
+            "Pacman lost a life"
+        );
 
         self.curr_lives -= 1; // Update the lives
     }
@@ -502,10 +666,10 @@ impl GameState {
     /// Reset all the pellets on the board
     pub fn reset_pellets(&mut self) {
         // Copy over pellet bit array
-        self.pellets = INIT_PELLETS;
+        self.pellets = self.config.maze.pellets;
 
         // Set the number of pellets to be the default
-        self.num_pellets = INIT_PELLET_COUNT;
+        self.num_pellets = self.config.maze.num_pellets;
     }
 
     /************************** Fruit Spawning Functions **************************/
@@ -554,20 +718,14 @@ impl GameState {
     /***************************** Step-Related Events ****************************/
 
     /// Helper function to handle step-related events, if the mode steps hit 0
-    pub fn handle_step_events(&mut self) {
+    pub fn handle_step_events(&mut self, events: &mut GameEvents) {
         // If the mode steps are 0, change the mode
         if self.mode_steps == 0 {
             match self.mode {
                 // CHASE -> SCATTER
-                GameMode::CHASE => {
-                    self.mode = GameMode::SCATTER;
-                    self.set_mode_steps(GameMode::SCATTER.duration());
-                }
+                GameMode::CHASE => self.switch_mode(GameMode::SCATTER, events),
                 // SCATTER -> CHASE
-                GameMode::SCATTER => {
-                    self.mode = GameMode::CHASE;
-                    self.set_mode_steps(GameMode::CHASE.duration());
-                }
+                GameMode::SCATTER => self.switch_mode(GameMode::CHASE, events),
             }
 
             // Reverse the directions of all ghosts to indicate a mode switch
@@ -576,21 +734,18 @@ impl GameState {
 
         // If the level steps are 0, add a penalty by speeding up the game
         if self.level_steps == 0 {
-            // Log the change to the terminal
-            #[cfg(feature = "std")]
-            if std::env::var("DISABLE_PACMAN_LOGGING").is_ok() {
-                println!("\x1b[31mGAME: Long-game penalty applied\x1b[0m");
-            }
+            // Log the change
+            game_log!(info, tick = self.curr_ticks; "Long-game penalty applied");
 
             // Drop the update period by 2
             self.set_update_period(u8::max(1, self.get_update_period().saturating_sub(2)));
 
             // Reset the level steps to the level penalty duration
-            self.set_level_steps(LEVEL_PENALTY_DURATION);
+            self.set_level_steps(self.config.level_penalty_duration);
         }
 
         // Decrement the mode steps
-        if self.get_num_pellets() >= ANGER_THRESHOLD1 {
+        if self.get_num_pellets() >= self.config.anger_threshold1 {
             self.decrement_mode_steps();
         }
 
@@ -601,3 +756,181 @@ impl GameState {
         self.decrement_fruit_steps();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The offsets of the red ghost's and Pacman's locations in a packet
+    const RED_OFFSET: usize = 13;
+    const PACMAN_OFFSET: usize = 29;
+
+    fn packet() -> [u8; PACKET_LEN] {
+        GameState::new_with_seed(7).to_packet()
+    }
+
+    #[test]
+    fn rejects_truncated_packet() {
+        assert_eq!(
+            GameState::from_bytes(&packet()[..100], 0),
+            Err(DecodeError::Truncated {
+                field: "pellets",
+                offset: 99,
+                len: 100
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_mode() {
+        let mut bytes = packet();
+        bytes[3] = 7;
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidValue {
+                field: "mode",
+                offset: 3,
+                value: 7
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_locations_off_maze() {
+        let mut bytes = packet();
+        bytes[RED_OFFSET..RED_OFFSET + 2].copy_from_slice(&[40, 5]);
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidValue {
+                field: "red location",
+                offset: RED_OFFSET,
+                value: 40
+            })
+        );
+
+        let mut bytes = packet();
+        bytes[PACMAN_OFFSET..PACMAN_OFFSET + 2].copy_from_slice(&[5, 30]);
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidValue {
+                field: "pacman location",
+                offset: PACMAN_OFFSET + 1,
+                value: 30
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_locations_in_walls() {
+        let mut bytes = packet();
+        bytes[RED_OFFSET..RED_OFFSET + 2].copy_from_slice(&[0, 0]);
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidValue {
+                field: "red location",
+                offset: RED_OFFSET,
+                value: 0
+            })
+        );
+
+        // Pacman can't be in the ghost house, though spawning ghosts can
+        let mut bytes = packet();
+        bytes[PACMAN_OFFSET..PACMAN_OFFSET + 2].copy_from_slice(&[13, 13]);
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidValue {
+                field: "pacman location",
+                offset: PACMAN_OFFSET,
+                value: 13
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_ghost_facing_dead_end() {
+        // A ghost at (1, 1) facing up, into the wall, that is not spawning
+        let mut bytes = packet();
+        bytes[RED_OFFSET..RED_OFFSET + 3].copy_from_slice(&[0xc1, 0x01, 0]);
+        assert_eq!(
+            GameState::from_bytes(&bytes, 0),
+            Err(DecodeError::InvalidLocation {
+                field: "red location",
+                offset: RED_OFFSET,
+                bytes: [0xc1, 0x01]
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_game_in_progress() {
+        let mut state = GameState::new_with_seed(7);
+        state.paused = false;
+        for _ in 0..1000 {
+            state.step();
+        }
+        let decoded = GameState::from_bytes_strict(&state.to_packet(), 0).unwrap();
+        assert_eq!(decoded.to_packet(), state.to_packet());
+    }
+
+    #[test]
+    fn replay_path_collects_along_the_way() {
+        let mut state = GameState::new_with_seed(7);
+        let events = state
+            .set_pacman_location_with((23, 10), LocationPolicy::ReplayPath)
+            .unwrap();
+        assert_eq!(state.pacman_loc.get_coords(), (23, 10));
+        assert_eq!(events.len(), 3);
+        assert_eq!(state.get_score(), 3 * state.config.pellet_points);
+        assert!(!state.pellet_at((23, 11)));
+    }
+
+    #[test]
+    fn teleport_only_collects_at_the_location() {
+        let mut state = GameState::new_with_seed(7);
+        let events = state
+            .set_pacman_location_with((23, 10), LocationPolicy::Teleport)
+            .unwrap();
+        assert_eq!(state.pacman_loc.get_coords(), (23, 10));
+        assert_eq!(events.len(), 1);
+        assert_eq!(state.get_score(), state.config.pellet_points);
+        assert!(state.pellet_at((23, 11)));
+    }
+
+    #[test]
+    fn max_distance_rejects_far_locations() {
+        let mut state = GameState::new_with_seed(7);
+        let before = state;
+        assert_eq!(
+            state.set_pacman_location_with((23, 10), LocationPolicy::MaxDistance(2)),
+            Err(LocationError::TooFar {
+                location: (23, 10),
+                distance: 3,
+                max_distance: 2
+            })
+        );
+        assert_eq!(state, before);
+
+        state
+            .set_pacman_location_with((23, 10), LocationPolicy::MaxDistance(3))
+            .unwrap();
+        assert_eq!(state.pacman_loc.get_coords(), (23, 10));
+    }
+
+    #[test]
+    fn no_policy_moves_dead_pacman() {
+        let mut state = GameState::new_with_seed(7);
+        state.pacman_loc = EMPTY_LOC;
+        let before = state;
+        for policy in [
+            LocationPolicy::ReplayPath,
+            LocationPolicy::Teleport,
+            LocationPolicy::MaxDistance(100),
+        ] {
+            assert_eq!(
+                state.set_pacman_location_with((23, 13), policy),
+                Err(LocationError::Dead { location: (23, 13) })
+            );
+            assert_eq!(state, before);
+        }
+    }
+}
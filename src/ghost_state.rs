@@ -2,10 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use GhostColor::*;
 
-use crate::{
-    location::LocationState,
-    variables::{EMPTY_LOC, GHOST_SCATTER_TARGETS, GHOST_SPAWN_LOCS, GHOST_TRAPPED_STEPS},
-};
+use crate::{config::GameConfig, location::LocationState, variables::EMPTY_LOC};
 
 /// Ghost colors
 #[repr(u8)]
@@ -51,13 +48,13 @@ pub struct GhostState {
 
 impl GhostState {
     // Create a new ghost state with given location and color values
-    pub fn new(color: GhostColor) -> Self {
+    pub fn new(color: GhostColor, config: &GameConfig) -> Self {
         Self {
             loc: EMPTY_LOC,
-            next_loc: GHOST_SPAWN_LOCS[color as usize],
-            scatter_target: GHOST_SCATTER_TARGETS[color as u8 as usize],
+            next_loc: config.maze.ghost_spawn_locs[color as usize],
+            scatter_target: config.ghost_scatter_targets[color as usize],
             color,
-            trapped_steps: GHOST_TRAPPED_STEPS[color as u8 as usize],
+            trapped_steps: config.ghost_trapped_steps[color as usize],
             fright_steps: 0,
             spawning: true,
             eaten: false,
@@ -151,11 +148,17 @@ impl GhostState {
         self.eaten
     }
 
-    pub fn from_bytes(color: GhostColor, location: LocationState, aux: u8, aux2: u8) -> Self {
+    pub fn from_bytes(
+        color: GhostColor,
+        location: LocationState,
+        aux: u8,
+        aux2: u8,
+        config: &GameConfig,
+    ) -> Self {
         let mut s = Self {
             loc: location,
             next_loc: location, // planning happens after all ghosts are initialized
-            scatter_target: GHOST_SCATTER_TARGETS[color as usize],
+            scatter_target: config.ghost_scatter_targets[color as usize],
             color,
             trapped_steps: 0, // aux2
             fright_steps: 0,  // aux
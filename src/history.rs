@@ -0,0 +1,319 @@
+use array_init::array_init;
+
+use crate::config::GameConfig;
+use crate::game_modes::GameMode;
+use crate::game_state::GameState;
+use crate::ghost_state::GhostState;
+use crate::location::LocationState;
+use crate::variables::MAZE_ROWS;
+
+/// When a [`History`] records the states that it is given.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RecordPolicy {
+    /// Record every state
+    #[default]
+    EveryTick,
+    /// Only record states on update boundaries, where [`GameState::update_ready`] is true
+    EveryUpdate,
+}
+
+// The parts of a game state that change as the game is played, which is everything but
+// the rules
+#[derive(Copy, Clone, Debug)]
+struct Snapshot {
+    curr_ticks: u32,
+    update_period: u8,
+    mode: GameMode,
+    paused: bool,
+    mode_steps: u8,
+    level_steps: u16,
+    curr_score: u16,
+    curr_level: u8,
+    curr_lives: u8,
+    pacman_loc: LocationState,
+    fruit_loc: LocationState,
+    fruit_steps: u8,
+    ghosts: [GhostState; 4],
+    ghost_combo: u8,
+    pellets: [u32; MAZE_ROWS],
+    num_pellets: u16,
+    seed: u64,
+}
+
+impl Snapshot {
+    fn new(state: &GameState) -> Self {
+        let GameState {
+            curr_ticks,
+            update_period,
+            mode,
+            paused,
+            mode_steps,
+            level_steps,
+            curr_score,
+            curr_level,
+            curr_lives,
+            pacman_loc,
+            fruit_loc,
+            fruit_steps,
+            ghosts,
+            ghost_combo,
+            pellets,
+            num_pellets,
+            seed,
+            config: _,
+        } = *state;
+        Self {
+            curr_ticks,
+            update_period,
+            mode,
+            paused,
+            mode_steps,
+            level_steps,
+            curr_score,
+            curr_level,
+            curr_lives,
+            pacman_loc,
+            fruit_loc,
+            fruit_steps,
+            ghosts,
+            ghost_combo,
+            pellets,
+            num_pellets,
+            seed,
+        }
+    }
+
+    fn restore(&self, config: GameConfig) -> GameState {
+        let Self {
+            curr_ticks,
+            update_period,
+            mode,
+            paused,
+            mode_steps,
+            level_steps,
+            curr_score,
+            curr_level,
+            curr_lives,
+            pacman_loc,
+            fruit_loc,
+            fruit_steps,
+            ghosts,
+            ghost_combo,
+            pellets,
+            num_pellets,
+            seed,
+        } = *self;
+        GameState {
+            curr_ticks,
+            update_period,
+            mode,
+            paused,
+            mode_steps,
+            level_steps,
+            curr_score,
+            curr_level,
+            curr_lives,
+            pacman_loc,
+            fruit_loc,
+            fruit_steps,
+            ghosts,
+            ghost_combo,
+            pellets,
+            num_pellets,
+            seed,
+            config,
+        }
+    }
+}
+
+/// A bounded history of past game states, for going back and trying something else.
+///
+/// Holds up to `N` snapshots; once full, recording a new one drops the oldest. Each
+/// snapshot restores the game state exactly.
+///
+/// The rules that the game is played with are stored once, and each snapshot only holds
+/// what changes as the game is played, which is about 220 bytes (mostly pellets). The
+/// snapshots are stored inline, so a history of a few thousand snapshots is too big for
+/// a thread's stack, and should be put in a `Box` or a `static` instead.
+#[derive(Clone, Debug)]
+pub struct History<const N: usize> {
+    // Ring buffer of snapshots, oldest first starting at `start`
+    snapshots: [Option<Snapshot>; N],
+    start: usize,
+    len: usize,
+    policy: RecordPolicy,
+    // The rules of the recorded states, if there are any
+    config: Option<GameConfig>,
+}
+
+impl<const N: usize> Default for History<N> {
+    fn default() -> Self {
+        Self::new(RecordPolicy::default())
+    }
+}
+
+impl<const N: usize> History<N> {
+    /// Creates an empty history.
+    pub fn new(policy: RecordPolicy) -> Self {
+        Self {
+            snapshots: array_init(|_| None),
+            start: 0,
+            len: 0,
+            policy,
+            config: None,
+        }
+    }
+
+    /// The maximum number of snapshots that the history holds
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of snapshots in the history
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the history has no snapshots
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The policy that decides which states are recorded
+    pub fn policy(&self) -> RecordPolicy {
+        self.policy
+    }
+
+    // The index into the ring buffer of the i-th oldest snapshot
+    fn index(&self, i: usize) -> usize {
+        (self.start + i) % N
+    }
+
+    /// Records a snapshot of the state, if the policy allows it, dropping the oldest
+    /// snapshot if the history is full. Returns whether the state was recorded.
+    ///
+    /// States played with different rules from the ones already in the history are not
+    /// recorded; [`History::clear`] the history to record a game with new rules.
+    pub fn record(&mut self, state: &GameState) -> bool {
+        if N == 0 || (self.policy == RecordPolicy::EveryUpdate && !state.update_ready()) {
+            return false;
+        }
+        match self.config {
+            Some(config) if self.len > 0 && config != state.config => return false,
+            _ => self.config = Some(state.config),
+        }
+        if self.len == N {
+            self.start = self.index(1);
+            self.len -= 1;
+        }
+        let i = self.index(self.len);
+        self.snapshots[i] = Some(Snapshot::new(state));
+        self.len += 1;
+        true
+    }
+
+    /// The most recent snapshot
+    pub fn latest(&self) -> Option<GameState> {
+        self.iter().next_back()
+    }
+
+    /// The most recent snapshot from at or before the given tick
+    pub fn at(&self, tick: u32) -> Option<GameState> {
+        self.iter().rev().find(|state| state.curr_ticks <= tick)
+    }
+
+    /// Iterates over the snapshots, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = GameState> + ExactSizeIterator + '_ {
+        (0..self.len).map(|i| {
+            let snapshot = self.snapshots[self.index(i)].as_ref().unwrap();
+            snapshot.restore(self.config.unwrap())
+        })
+    }
+
+    /// Drops every snapshot more recent than the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.len -= 1;
+            let i = self.index(self.len);
+            self.snapshots[i] = None;
+        }
+    }
+
+    /// Drops every snapshot.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+        self.start = 0;
+    }
+
+    /// Drops the most recent snapshot, returning the one before it, which becomes the
+    /// most recent.
+    ///
+    /// Does nothing if there is no earlier snapshot to go back to.
+    pub fn undo(&mut self) -> Option<GameState> {
+        if self.len < 2 {
+            return None;
+        }
+        self.truncate(self.len - 1);
+        self.latest()
+    }
+
+    // The number of snapshots up to and including the most recent one at or before the tick
+    fn len_at(&self, tick: u32) -> Option<usize> {
+        self.iter()
+            .rposition(|state| state.curr_ticks <= tick)
+            .map(|i| i + 1)
+    }
+
+    /// Drops every snapshot after the given tick, returning the most recent remaining
+    /// snapshot, to continue the game from.
+    ///
+    /// Does nothing if there is no snapshot from at or before the tick.
+    pub fn rewind_to(&mut self, tick: u32) -> Option<GameState> {
+        let len = self.len_at(tick)?;
+        self.truncate(len);
+        self.latest()
+    }
+
+    /// Creates a copy of the history as it was at the given tick, leaving this one
+    /// untouched, so that the game can be continued in a different way from there.
+    ///
+    /// The most recent snapshot of the branch is the state to continue from.
+    pub fn branch(&self, tick: u32) -> Option<Self> {
+        let len = self.len_at(tick)?;
+        let mut branch = self.clone();
+        branch.truncate(len);
+        Some(branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_restore_states_exactly() {
+        let mut history = History::<8>::new(RecordPolicy::EveryTick);
+        let mut state = GameState::new_with_seed(5);
+        let mut states = Vec::new();
+        for _ in 0..20 {
+            state.step();
+            history.record(&state);
+            states.push(state);
+        }
+        assert!(history.iter().eq(states[12..].iter().copied()));
+        assert!(core::mem::size_of::<Option<Snapshot>>() < 240);
+    }
+
+    #[test]
+    fn skips_states_with_other_rules() {
+        let mut history = History::<8>::new(RecordPolicy::EveryTick);
+        let state = GameState::new();
+        let mut other = state;
+        other.config.init_lives += 1;
+        assert!(history.record(&state));
+        assert!(!history.record(&other));
+        history.clear();
+        assert!(history.record(&other));
+        assert_eq!(history.latest(), Some(other));
+    }
+}
@@ -1,9 +1,35 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod codec;
+pub mod command;
+pub mod config;
+pub mod danger;
+pub mod distance;
+pub mod env;
+pub mod events;
 mod game_helpers;
 pub mod game_modes;
 pub mod game_state;
 mod ghost_helpers;
 pub mod ghost_state;
+pub mod history;
 pub mod location;
+mod logging;
+pub mod maze;
+pub mod obs;
+pub mod path;
+pub mod predict;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod search;
+pub mod snapshot;
 pub mod variables;
+#[cfg(feature = "wasm")]
+mod wasm;
@@ -1,5 +1,4 @@
-use core2::io;
-use core2::io::ErrorKind;
+use core::fmt;
 use serde::{Deserialize, Serialize};
 
 use Direction::*;
@@ -7,13 +6,50 @@ use Direction::*;
 use crate::game_helpers::Position;
 use crate::variables::EMPTY_LOC;
 
+/// Whether the position holds a super pellet in the standard maze (see [`crate::maze::Maze`])
 pub fn is_super_pellet(position: Position) -> bool {
     let (row, col) = position;
     ((row == 3) || (row == 23)) && ((col == 1) || (col == 26))
 }
 
+/// The super pellet positions in the standard maze
 pub const SUPER_PELLETS: [Position; 4] = [(3, 1), (3, 26), (23, 1), (23, 26)];
 
+/// An error from decoding a location whose bytes encode an impossible direction.
+///
+/// Decoding a whole packet reports this as [`DecodeError::InvalidLocation`](crate::codec::DecodeError::InvalidLocation),
+/// along with where the location is in the packet.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LocationDecodeError {
+    /// The bytes of the location
+    pub bytes: [u8; 2],
+}
+
+impl fmt::Display for LocationDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid location {:02x?}", self.bytes)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LocationDecodeError {}
+
+/// An error from converting a number that isn't one of the [`Direction`]s.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidDirection {
+    /// The number that was converted
+    pub value: u8,
+}
+
+impl fmt::Display for InvalidDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid direction {}", self.value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidDirection {}
+
 /// Directions
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Ord, Eq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -26,7 +62,7 @@ pub enum Direction {
 }
 
 impl TryFrom<u8> for Direction {
-    type Error = ();
+    type Error = InvalidDirection;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
@@ -35,7 +71,7 @@ impl TryFrom<u8> for Direction {
             2 => Ok(Down),
             3 => Ok(Right),
             4 => Ok(Stay),
-            _ => Err(()),
+            value => Err(InvalidDirection { value }),
         }
     }
 }
@@ -149,8 +185,9 @@ impl LocationState {
         i8s.map(|x| x.to_be_bytes()[0])
     }
 
-    pub fn from_bytes(bytes: [u8; 2]) -> io::Result<Self> {
-        let bytes = bytes.map(|x| i8::from_be_bytes([x]));
+    /// Decodes a location from the two bytes that encode it in a packet
+    pub fn from_bytes(raw: [u8; 2]) -> Result<Self, LocationDecodeError> {
+        let bytes = raw.map(|x| i8::from_be_bytes([x]));
 
         // Get the row and column bytes
         let row: i8 = bytes[0] & 0b00111111;
@@ -165,7 +202,7 @@ impl LocationState {
             (1, 0) => Down,
             (0, -1) => Left,
             (0, 1) => Right,
-            _ => return Err(io::Error::from(ErrorKind::InvalidInput)),
+            _ => return Err(LocationDecodeError { bytes: raw }),
         };
 
         Ok(Self { row, col, dir })
@@ -0,0 +1,10 @@
+/// Logs a game event through the `log` crate, with structured key-values such as
+/// `tick = ..., old = ..., new = ...`. Does nothing unless the `log` feature is enabled.
+macro_rules! game_log {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "log")]
+        log::$level!(target: "pacbot_rs::game", $($arg)+);
+    };
+}
+
+pub(crate) use game_log;
@@ -0,0 +1,485 @@
+use array_init::array_init;
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_helpers::{get_bit_u32, modify_bit_u32, Position};
+use crate::location::{Direction, LocationState};
+use crate::variables::*;
+
+/// The layout of a maze: walls, pellets, the ghost house and spawn points.
+///
+/// Mazes can be described in a plain-text format, with one line per row:
+///
+/// | Char    | Meaning                                                       |
+/// |---------|---------------------------------------------------------------|
+/// | `#`     | Wall                                                          |
+/// | `.`     | Pellet                                                        |
+/// | `o`     | Super pellet                                                  |
+/// | ` `     | Empty walkable cell                                           |
+/// | `P`     | Pacman spawn (empty walkable cell)                            |
+/// | `F`     | Fruit spawn (empty walkable cell)                             |
+/// | `G`     | Ghost house (a wall for Pacman, open to spawning ghosts)      |
+/// | `-`     | Ghost house exit (a wall for Pacman, open to spawning ghosts) |
+/// | `0`     | Red ghost spawn, just outside the exit (empty walkable cell)  |
+/// | `1`-`3` | Pink, cyan and orange ghost spawns (ghost house cells)        |
+///
+/// Mazes may be smaller than [`MAZE_ROWS`] x [`MAZE_COLS`]; any missing cells are walls.
+/// Trailing spaces are significant, since a space is a walkable cell.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, PartialEq, Eq)]
+pub struct Maze {
+    /// Walls, with each uint32 acting as a bit array (column 0 at bit 0)
+    pub walls: [u32; MAZE_ROWS],
+
+    /// Pellets at the start of each level, including super pellets
+    pub pellets: [u32; MAZE_ROWS],
+
+    /// The subset of pellets that are super pellets
+    pub super_pellets: [u32; MAZE_ROWS],
+
+    /// Cells that belong to the ghost house
+    pub ghost_house: [u32; MAZE_ROWS],
+
+    /// The number of pellets at the start of each level
+    pub num_pellets: u16,
+
+    /// The coordinates where the ghost house exit is located
+    pub ghost_house_exit_pos: Position,
+
+    /// Spawn position for Pacman
+    pub pacman_spawn_loc: LocationState,
+
+    /// Spawn position for the fruit
+    pub fruit_spawn_loc: LocationState,
+
+    /// Spawn positions for the ghosts, indexed by color
+    pub ghost_spawn_locs: [LocationState; 4],
+}
+
+/// An error encountered while parsing a maze.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MazeError {
+    /// The maze has more than [`MAZE_ROWS`] rows
+    TooManyRows,
+    /// A row has more than [`MAZE_COLS`] columns
+    RowTooLong { row: usize },
+    /// A character that is not part of the maze format
+    UnknownCell { row: usize, col: usize, ch: char },
+    /// A spawn point or ghost house exit was given more than once
+    DuplicateMarker { row: usize, col: usize, ch: char },
+    /// A required spawn point or ghost house exit was not given
+    MissingMarker { ch: char },
+    /// The ghost house exit is not next to the ghost house, red's spawn point is not next
+    /// to the exit, or a ghost spawn point faces a cell that the ghost can't move to
+    MisplacedMarker { row: usize, col: usize, ch: char },
+    /// A cell has fewer than two neighbors that ghosts can move to, so ghosts, which
+    /// never turn back, would be stuck there
+    DeadEnd { row: usize, col: usize },
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::TooManyRows => write!(f, "maze has more than {MAZE_ROWS} rows"),
+            MazeError::RowTooLong { row } => {
+                write!(f, "row {row} has more than {MAZE_COLS} columns")
+            }
+            MazeError::UnknownCell { row, col, ch } => {
+                write!(f, "unknown cell {ch:?} at ({row}, {col})")
+            }
+            MazeError::DuplicateMarker { row, col, ch } => {
+                write!(f, "duplicate {ch:?} at ({row}, {col})")
+            }
+            MazeError::MissingMarker { ch } => write!(f, "maze has no {ch:?}"),
+            MazeError::MisplacedMarker { row, col, ch } => {
+                write!(f, "misplaced {ch:?} at ({row}, {col})")
+            }
+            MazeError::DeadEnd { row, col } => write!(f, "dead end at ({row}, {col})"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MazeError {}
+
+/// The standard competition maze, which configurations refer to by default.
+pub static STANDARD_MAZE: Maze = Maze::standard();
+
+/*
+Game configurations refer to their maze, rather than holding a copy, so that game
+states stay small enough to copy cheaply during search. Mazes are given a static
+lifetime: the standard maze is a static, and other mazes are either statics of their
+own or are leaked, once, with Maze::into_static.
+
+Decoding a config never allocates a maze. It only finds one that already has a static
+lifetime: the standard maze, a maze in the list that the caller passes in, or (with
+std) a maze that has been registered. Untrusted input therefore can't leak memory, and
+mazes that nobody has asked for are rejected.
+*/
+
+// Mazes that have been registered, other than the standard maze
+#[cfg(feature = "std")]
+static REGISTERED_MAZES: std::sync::Mutex<Vec<&'static Maze>> = std::sync::Mutex::new(Vec::new());
+
+// Lock the registered mazes. The list is only ever pushed to, so a poisoned lock still
+// holds valid mazes.
+#[cfg(feature = "std")]
+fn registered_mazes() -> std::sync::MutexGuard<'static, Vec<&'static Maze>> {
+    REGISTERED_MAZES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Find a static maze identical to the given one: the standard maze, one of the given
+// mazes, or a registered maze
+pub(crate) fn find_static(maze: &Maze, mazes: &[&'static Maze]) -> Option<&'static Maze> {
+    if *maze == STANDARD_MAZE {
+        return Some(&STANDARD_MAZE);
+    }
+    if let Some(&found) = mazes.iter().find(|&&found| found == maze) {
+        return Some(found);
+    }
+    #[cfg(feature = "std")]
+    if let Some(&found) = registered_mazes().iter().find(|&&found| found == maze) {
+        return Some(found);
+    }
+    None
+}
+
+impl Default for Maze {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Maze {
+    /// The standard competition maze.
+    pub const fn standard() -> Self {
+        // The ghost house occupies rows 13 and 14, columns 11 to 15
+        let mut ghost_house = [0; MAZE_ROWS];
+        ghost_house[13] = 0b11111 << 11;
+        ghost_house[14] = 0b11111 << 11;
+
+        // The super pellets are in rows 3 and 23, columns 1 and 26
+        let mut super_pellets = [0; MAZE_ROWS];
+        super_pellets[3] = (1 << 1) | (1 << 26);
+        super_pellets[23] = (1 << 1) | (1 << 26);
+
+        Self {
+            walls: INIT_WALLS,
+            pellets: INIT_PELLETS,
+            super_pellets,
+            ghost_house,
+            num_pellets: INIT_PELLET_COUNT,
+            ghost_house_exit_pos: GHOST_HOUSE_EXIT_POS,
+            pacman_spawn_loc: PACMAN_SPAWN_LOC,
+            fruit_spawn_loc: FRUIT_SPAWN_LOC,
+            ghost_spawn_locs: GHOST_SPAWN_LOCS,
+        }
+    }
+
+    /// Gives the maze a static lifetime, so that a [`GameConfig`](crate::config::GameConfig)
+    /// can refer to it, and registers it (see [`Maze::register`]).
+    ///
+    /// Each distinct maze is leaked the first time this is called with it, and then
+    /// shared, however many times this is called.
+    #[cfg(feature = "std")]
+    pub fn into_static(self) -> &'static Maze {
+        if self == STANDARD_MAZE {
+            return &STANDARD_MAZE;
+        }
+        let mut mazes = registered_mazes();
+        if let Some(&found) = mazes.iter().find(|&&found| *found == self) {
+            return found;
+        }
+        let maze: &'static Maze = Box::leak(Box::new(self));
+        mazes.push(maze);
+        maze
+    }
+
+    /// Registers a maze, so that configs which use it can be decoded from snapshots,
+    /// replays and serde formats.
+    ///
+    /// Without `std`, snapshots can be decoded with a list of mazes instead (see
+    /// [`GameState::from_snapshot_with_mazes`](crate::game_state::GameState::from_snapshot_with_mazes)).
+    #[cfg(feature = "std")]
+    pub fn register(&'static self) {
+        let mut mazes = registered_mazes();
+        if *self != STANDARD_MAZE && !mazes.contains(&self) {
+            mazes.push(self);
+        }
+    }
+
+    /// Parse a maze from its text format (see [`Maze`]).
+    ///
+    /// Spawn points face the same directions as in the standard maze, so the ghost house
+    /// must have room for the ghosts to move the way they do there. Mazes that would leave
+    /// a ghost with nowhere to go, such as those with dead ends, are rejected.
+    pub fn parse(text: &str) -> Result<Self, MazeError> {
+        let mut walls = [u32::MAX >> (32 - MAZE_COLS); MAZE_ROWS];
+        let mut pellets = [0; MAZE_ROWS];
+        let mut super_pellets = [0; MAZE_ROWS];
+        let mut ghost_house = [0; MAZE_ROWS];
+        let mut num_pellets: u16 = 0;
+
+        // Markers, in the order: P, F, -, 0, 1, 2, 3
+        const MARKERS: [char; 7] = ['P', 'F', '-', '0', '1', '2', '3'];
+        let mut markers: [Option<Position>; 7] = [None; 7];
+
+        for (row, line) in text.lines().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if row >= MAZE_ROWS {
+                return Err(MazeError::TooManyRows);
+            }
+            for (col, ch) in line.chars().enumerate() {
+                if col >= MAZE_COLS {
+                    return Err(MazeError::RowTooLong { row });
+                }
+                let (wall, pellet, super_pellet, house) = match ch {
+                    '#' => (true, false, false, false),
+                    '.' => (false, true, false, false),
+                    'o' => (false, true, true, false),
+                    ' ' | 'P' | 'F' | '0' => (false, false, false, false),
+                    '-' => (true, false, false, false),
+                    'G' | '1' | '2' | '3' => (true, false, false, true),
+                    _ => return Err(MazeError::UnknownCell { row, col, ch }),
+                };
+                modify_bit_u32(&mut walls[row], col, wall);
+                modify_bit_u32(&mut pellets[row], col, pellet);
+                modify_bit_u32(&mut super_pellets[row], col, super_pellet);
+                modify_bit_u32(&mut ghost_house[row], col, house);
+                if pellet {
+                    num_pellets += 1;
+                }
+                if let Some(i) = MARKERS.iter().position(|&m| m == ch) {
+                    if markers[i].is_some() {
+                        return Err(MazeError::DuplicateMarker { row, col, ch });
+                    }
+                    markers[i] = Some((row as i8, col as i8));
+                }
+            }
+        }
+
+        let mut positions = [(0, 0); 7];
+        for (i, marker) in markers.into_iter().enumerate() {
+            positions[i] = marker.ok_or(MazeError::MissingMarker { ch: MARKERS[i] })?;
+        }
+        let [pacman, fruit, exit, red, pink, cyan, orange] = positions;
+
+        let spawn_loc = |(row, col): Position, dir| LocationState::new(row, col, dir);
+        let maze = Self {
+            walls,
+            pellets,
+            super_pellets,
+            ghost_house,
+            num_pellets,
+            ghost_house_exit_pos: exit,
+            pacman_spawn_loc: spawn_loc(pacman, PACMAN_SPAWN_LOC.dir),
+            fruit_spawn_loc: spawn_loc(fruit, FRUIT_SPAWN_LOC.dir),
+            ghost_spawn_locs: array_init(|i| {
+                spawn_loc([red, pink, cyan, orange][i], GHOST_SPAWN_LOCS[i].dir)
+            }),
+        };
+        maze.check_ghost_moves()?;
+        Ok(maze)
+    }
+
+    // Check that ghosts, which never turn back, always have somewhere to move to
+    fn check_ghost_moves(&self) -> Result<(), MazeError> {
+        let neighbors = |(row, col): Position| {
+            Direction::all_except_stay().map(|dir| (row + dir.vector().0, col + dir.vector().1))
+        };
+        let misplaced = |(row, col): Position, ch| MazeError::MisplacedMarker {
+            row: row as usize,
+            col: col as usize,
+            ch,
+        };
+
+        // Ghosts leave the ghost house through the exit, which leads to red's spawn point
+        let exit = self.ghost_house_exit_pos;
+        if !neighbors(exit).iter().any(|&pos| self.ghost_house_at(pos)) {
+            return Err(misplaced(exit, '-'));
+        }
+        let red = self.ghost_spawn_locs[0].get_coords();
+        if !neighbors(exit).contains(&red) {
+            return Err(misplaced(red, '0'));
+        }
+
+        // Each ghost starts out moving in the direction that its spawn point faces
+        for (i, loc) in self.ghost_spawn_locs.iter().enumerate() {
+            if !self.open_to_ghost(loc.get_ahead_coords(1), true) {
+                return Err(misplaced(loc.get_coords(), (b'0' + i as u8) as char));
+            }
+        }
+
+        // Every cell that ghosts can be at needs a way out other than the way they came
+        // in, counting the ghost house and its exit only for ghosts inside them
+        for row in 0..MAZE_ROWS as i8 {
+            for col in 0..MAZE_COLS as i8 {
+                let pos = (row, col);
+                if !self.open_to_ghost(pos, true) {
+                    continue;
+                }
+                let spawning = self.wall_at(pos);
+                let exits = neighbors(pos)
+                    .iter()
+                    .filter(|&&next| self.open_to_ghost(next, spawning))
+                    .count();
+                if exits < 2 {
+                    return Err(MazeError::DeadEnd {
+                        row: row as usize,
+                        col: col as usize,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Determines if a position is within the bounds of the maze
+    fn in_bounds(pos: Position) -> bool {
+        let (row, col) = pos;
+        (row >= 0 && row < MAZE_ROWS as i8) && (col >= 0 && col < MAZE_COLS as i8)
+    }
+
+    // Returns the bit of a bit array at a given position, or `outside` if out of bounds
+    fn bit_at(bits: &[u32; MAZE_ROWS], pos: Position, outside: bool) -> bool {
+        if !Self::in_bounds(pos) {
+            return outside;
+        }
+        get_bit_u32(bits[pos.0 as usize], pos.1 as usize)
+    }
+
+    /// Determines if a wall is at a given location
+    pub fn wall_at(&self, pos: Position) -> bool {
+        Self::bit_at(&self.walls, pos, true)
+    }
+
+    /// Determines if a pellet is at a given location at the start of a level
+    pub fn pellet_at(&self, pos: Position) -> bool {
+        Self::bit_at(&self.pellets, pos, false)
+    }
+
+    /// Determines if a super pellet is at a given location
+    pub fn super_pellet_at(&self, pos: Position) -> bool {
+        Self::bit_at(&self.super_pellets, pos, false)
+    }
+
+    /// Determines if the ghost house is at a given location
+    pub fn ghost_house_at(&self, pos: Position) -> bool {
+        Self::bit_at(&self.ghost_house, pos, false)
+    }
+
+    // Determines if a ghost can move to a given location, as when planning ghost moves:
+    // any cell that is not a wall, as well as the ghost house and its exit while the
+    // ghost is spawning
+    pub(crate) fn open_to_ghost(&self, pos: Position, spawning: bool) -> bool {
+        !self.wall_at(pos)
+            || spawning && (self.ghost_house_at(pos) || pos == self.ghost_house_exit_pos)
+    }
+}
+
+impl fmt::Display for Maze {
+    /// Writes the maze in its text format (see [`Maze`]).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..MAZE_ROWS as i8 {
+            for col in 0..MAZE_COLS as i8 {
+                let pos = (row, col);
+                let spawns = self.ghost_spawn_locs.map(|loc| loc.get_coords());
+                let ch = if let Some(i) = spawns.iter().position(|&p| p == pos) {
+                    (b'0' + i as u8) as char
+                } else if pos == self.pacman_spawn_loc.get_coords() {
+                    'P'
+                } else if pos == self.fruit_spawn_loc.get_coords() {
+                    'F'
+                } else if pos == self.ghost_house_exit_pos {
+                    '-'
+                } else if self.ghost_house_at(pos) {
+                    'G'
+                } else if self.wall_at(pos) {
+                    '#'
+                } else if self.super_pellet_at(pos) {
+                    'o'
+                } else if self.pellet_at(pos) {
+                    '.'
+                } else {
+                    ' '
+                };
+                write!(f, "{ch}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::game_state::GameState;
+
+    const SMALL: &str = "\
+###########
+#o.......o#
+#.##.#.##.#
+#....0....#
+#.###-###.#
+#.#GG1GG#.#
+#.#2GGG3#.#
+#.#######.#
+#....P....#
+#o..F....o#
+###########
+";
+
+    #[test]
+    fn text_round_trip() {
+        assert_eq!(Maze::parse(&STANDARD_MAZE.to_string()), Ok(STANDARD_MAZE));
+
+        let small = Maze::parse(SMALL).unwrap();
+        assert_eq!(small.num_pellets, 45);
+        assert_eq!(Maze::parse(&small.to_string()), Ok(small));
+    }
+
+    #[test]
+    fn rejects_dead_end() {
+        let pocket = SMALL.replacen("#o.......o#", "#o..#....o#", 1);
+        assert_eq!(
+            Maze::parse(&pocket),
+            Err(MazeError::DeadEnd { row: 1, col: 3 })
+        );
+    }
+
+    #[test]
+    fn small_maze_plays() {
+        let config = GameConfig {
+            maze: Maze::parse(SMALL).unwrap().into_static(),
+            ..GameConfig::new()
+        };
+        let mut rng = SmallRng::seed_from_u64(3);
+        for seed in 0..5 {
+            let mut state = GameState::new_with_config(seed, config);
+            state.paused = false;
+            for _ in 0..3000 {
+                if state.is_game_over() {
+                    break;
+                }
+                if state.update_ready() {
+                    let dir: u8 = rng.gen_range(0..5);
+                    state.move_pacman_dir(Direction::try_from(dir).unwrap());
+                }
+                state.step();
+                for ghost in &state.ghosts {
+                    assert!(
+                        ghost.loc.is_empty()
+                            || config.maze.open_to_ghost(ghost.loc.get_coords(), true)
+                    );
+                }
+            }
+        }
+    }
+}
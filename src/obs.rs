@@ -0,0 +1,171 @@
+use crate::game_helpers::get_bit_u32;
+use crate::game_modes::GameMode;
+use crate::game_state::GameState;
+use crate::ghost_state::GhostColor;
+use crate::location::LocationState;
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+/// The planes of an observation, in the order that they are laid out.
+///
+/// Each plane is [`MAZE_ROWS`] by [`MAZE_COLS`]. Most planes mark cells with a 1; the
+/// scalar planes (`Mode`, `ModeSteps` and `FrightSteps`) hold the same value everywhere.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Channel {
+    /// Walls
+    Walls = 0,
+    /// Regular pellets
+    Pellets = 1,
+    /// Super pellets
+    SuperPellets = 2,
+    /// The fruit, while it is on the maze
+    Fruit = 3,
+    /// Pacman
+    Pacman = 4,
+    /// The red ghost
+    RedGhost = 5,
+    /// The pink ghost
+    PinkGhost = 6,
+    /// The cyan ghost
+    CyanGhost = 7,
+    /// The orange ghost
+    OrangeGhost = 8,
+    /// Every frightened ghost
+    FrightenedGhosts = 9,
+    /// The location that each ghost plans to move to next
+    GhostNextLocs = 10,
+    /// The mode: 0 for scatter, 1 for chase
+    Mode = 11,
+    /// The steps left in the current mode
+    ModeSteps = 12,
+    /// The steps left until the ghosts stop being frightened
+    FrightSteps = 13,
+}
+
+/// The number of planes in an observation
+pub const NUM_CHANNELS: usize = 14;
+
+/// The number of cells in a plane
+pub const PLANE_LEN: usize = MAZE_ROWS * MAZE_COLS;
+
+/// The length of an observation
+pub const OBS_LEN: usize = NUM_CHANNELS * PLANE_LEN;
+
+/// A type that observations can be encoded as.
+pub trait ObsValue: Copy {
+    /// The value of an unmarked cell
+    const ZERO: Self;
+    /// The value of a marked cell
+    const ONE: Self;
+
+    /// The value of a scalar channel, given the value and its maximum
+    fn scalar(value: u8, max: u8) -> Self;
+}
+
+/// Scalars are scaled to be between 0 and 1.
+impl ObsValue for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn scalar(value: u8, max: u8) -> Self {
+        value as f32 / max.max(1) as f32
+    }
+}
+
+/// Scalars are left as they are.
+impl ObsValue for u8 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn scalar(value: u8, _max: u8) -> Self {
+        value
+    }
+}
+
+// Mark a location on a plane, if it is on the maze
+fn mark<T: ObsValue>(buf: &mut [T; OBS_LEN], channel: Channel, loc: LocationState) {
+    let (row, col) = loc.get_coords();
+    if (0..MAZE_ROWS as i8).contains(&row) && (0..MAZE_COLS as i8).contains(&col) {
+        let index = channel as usize * PLANE_LEN + row as usize * MAZE_COLS + col as usize;
+        buf[index] = T::ONE;
+    }
+}
+
+// Mark every cell whose bit is set on a plane
+fn mark_bits<T: ObsValue>(buf: &mut [T; OBS_LEN], channel: Channel, bits: impl Fn(usize) -> u32) {
+    let plane = &mut buf[channel as usize * PLANE_LEN..][..PLANE_LEN];
+    for (row, cells) in plane.chunks_exact_mut(MAZE_COLS).enumerate() {
+        let row_bits = bits(row);
+        for (col, cell) in cells.iter_mut().enumerate() {
+            if get_bit_u32(row_bits, col) {
+                *cell = T::ONE;
+            }
+        }
+    }
+}
+
+// Fill a plane with a single value
+fn fill<T: ObsValue>(buf: &mut [T; OBS_LEN], channel: Channel, value: T) {
+    buf[channel as usize * PLANE_LEN..][..PLANE_LEN].fill(value);
+}
+
+impl GameState {
+    /// Encodes the game state as planes for a neural network, into a buffer (see
+    /// [`Channel`] for the layout).
+    pub fn encode_obs<T: ObsValue>(&self, buf: &mut [T; OBS_LEN]) {
+        buf.fill(T::ZERO);
+
+        // Maze
+        let walls = &self.config.maze.walls;
+        let super_pellets = &self.config.maze.super_pellets;
+        mark_bits(buf, Channel::Walls, |row| walls[row]);
+        mark_bits(buf, Channel::Pellets, |row| {
+            self.pellets[row] & !super_pellets[row]
+        });
+        mark_bits(buf, Channel::SuperPellets, |row| {
+            self.pellets[row] & super_pellets[row]
+        });
+
+        // Fruit and Pacman
+        if self.fruit_exists() {
+            mark(buf, Channel::Fruit, self.fruit_loc);
+        }
+        mark(buf, Channel::Pacman, self.pacman_loc);
+
+        // Ghosts
+        let mut fright_steps = 0;
+        for ghost in &self.ghosts {
+            let channel = match ghost.color {
+                GhostColor::Red => Channel::RedGhost,
+                GhostColor::Pink => Channel::PinkGhost,
+                GhostColor::Cyan => Channel::CyanGhost,
+                GhostColor::Orange => Channel::OrangeGhost,
+            };
+            mark(buf, channel, ghost.loc);
+            if ghost.is_frightened() {
+                mark(buf, Channel::FrightenedGhosts, ghost.loc);
+            }
+            mark(buf, Channel::GhostNextLocs, ghost.next_loc);
+            fright_steps = fright_steps.max(ghost.fright_steps);
+        }
+
+        // Scalars
+        let mode = match self.mode {
+            GameMode::SCATTER => T::ZERO,
+            GameMode::CHASE => T::ONE,
+        };
+        fill(buf, Channel::Mode, mode);
+        let mode_duration = self.config.mode_duration(self.mode);
+        fill(
+            buf,
+            Channel::ModeSteps,
+            T::scalar(self.mode_steps, mode_duration),
+        );
+        let max_fright_steps = self.config.ghost_fright_steps;
+        fill(
+            buf,
+            Channel::FrightSteps,
+            T::scalar(fright_steps, max_fright_steps),
+        );
+    }
+}
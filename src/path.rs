@@ -0,0 +1,260 @@
+use core::fmt;
+use core::iter::Copied;
+use core::slice;
+
+use crate::game_helpers::Position;
+use crate::game_state::GameState;
+use crate::location::{Direction, LocationState};
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+/// The longest path that can be found, which visits every cell of the maze once
+pub const MAX_PATH_LEN: usize = MAZE_ROWS * MAZE_COLS;
+
+/*
+Path searches that never touch the heap, so that the same planner can run on the host
+and on the robot. All bookkeeping lives in arrays with one entry per cell of the maze
+(a few kilobytes on the stack), and a path is stored as its start and the directions
+taken from there.
+
+Both searches look at neighbors in the order up, left, down, right. Breadth-first
+search finds the same paths as GameState::bfs always has; A* may break ties between
+equally short paths differently, but expands fewer cells when the target is close.
+*/
+
+/// A path through the maze, stored as the directions taken from its start.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Path {
+    start: Position,
+    end: Position,
+    dirs: [Direction; MAX_PATH_LEN],
+    len: usize,
+}
+
+/// An iterator over the steps of a [`Path`], created by [`Path::steps`].
+#[derive(Clone, Debug)]
+pub struct Steps<'a> {
+    pos: Position,
+    dirs: slice::Iter<'a, Direction>,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = LocationState;
+
+    fn next(&mut self) -> Option<LocationState> {
+        let dir = *self.dirs.next()?;
+        let (row, col) = self.pos;
+        let (d_row, d_col) = dir.vector();
+        self.pos = (row + d_row, col + d_col);
+        Some(LocationState { row, col, dir })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dirs.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Steps<'_> {}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Path")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("dirs", &self.as_directions())
+            .finish()
+    }
+}
+
+impl Path {
+    // Walk back from the end of a search to its start, following the direction that
+    // each cell was entered from
+    fn trace(
+        start: Position,
+        end: Position,
+        entered: &[[Option<Direction>; MAZE_COLS]; MAZE_ROWS],
+    ) -> Self {
+        let mut path = Self {
+            start,
+            end,
+            dirs: [Direction::Stay; MAX_PATH_LEN],
+            len: 0,
+        };
+        let mut pos = end;
+        while pos != start {
+            let dir = entered[pos.0 as usize][pos.1 as usize].unwrap();
+            let (d_row, d_col) = dir.vector();
+            pos = (pos.0 - d_row, pos.1 - d_col);
+            path.dirs[path.len] = dir;
+            path.len += 1;
+        }
+        path.dirs[..path.len].reverse();
+        path
+    }
+
+    /// The number of moves along the path
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the path starts where it ends
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Where the path starts
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Where the path ends
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    /// The directions of the moves along the path
+    pub fn as_directions(&self) -> &[Direction] {
+        &self.dirs[..self.len]
+    }
+
+    /// An iterator over the directions of the moves along the path
+    pub fn directions(&self) -> Copied<slice::Iter<'_, Direction>> {
+        self.as_directions().iter().copied()
+    }
+
+    /// An iterator over the moves along the path, each of which is the location that the
+    /// move starts from and its direction (the same as the steps of [`GameState::bfs`])
+    pub fn steps(&self) -> Steps<'_> {
+        Steps {
+            pos: self.start,
+            dirs: self.as_directions().iter(),
+        }
+    }
+}
+
+// Determines if a position is within the bounds of the maze
+fn on_maze(pos: Position) -> bool {
+    (0..MAZE_ROWS as i8).contains(&pos.0) && (0..MAZE_COLS as i8).contains(&pos.1)
+}
+
+// The cell that a move leads to, if it is on the maze
+fn neighbor(pos: Position, dir: Direction) -> Option<Position> {
+    let (d_row, d_col) = dir.vector();
+    let next = (pos.0 + d_row, pos.1 + d_col);
+    on_maze(next).then_some(next)
+}
+
+/// Finds a shortest path between two cells with a breadth-first search, moving only
+/// through cells that are `walkable`.
+///
+/// The start does not have to be walkable, but must be on the maze. Returns `None` if
+/// there is no path.
+pub fn bfs(start: Position, end: Position, walkable: impl Fn(Position) -> bool) -> Option<Path> {
+    let mut entered = [[None; MAZE_COLS]; MAZE_ROWS];
+    if start == end {
+        return Some(Path::trace(start, end, &entered));
+    }
+    if !on_maze(start) {
+        return None;
+    }
+
+    let mut visited = [[false; MAZE_COLS]; MAZE_ROWS];
+    let mut queue = [(0, 0); MAX_PATH_LEN];
+    visited[start.0 as usize][start.1 as usize] = true;
+    queue[0] = start;
+    let (mut head, mut tail) = (0, 1);
+
+    while head < tail {
+        let pos = queue[head];
+        head += 1;
+        for dir in Direction::all_except_stay() {
+            let Some(next) = neighbor(pos, dir) else {
+                continue;
+            };
+            let (row, col) = (next.0 as usize, next.1 as usize);
+            if visited[row][col] || !walkable(next) {
+                continue;
+            }
+            visited[row][col] = true;
+            entered[row][col] = Some(dir);
+            if next == end {
+                return Some(Path::trace(start, end, &entered));
+            }
+            queue[tail] = next;
+            tail += 1;
+        }
+    }
+    None
+}
+
+/// Finds a shortest path between two cells with A*, guided by the Manhattan distance
+/// to the end, moving only through cells that are `walkable`.
+///
+/// The start does not have to be walkable, but must be on the maze. Returns `None` if
+/// there is no path.
+pub fn astar(start: Position, end: Position, walkable: impl Fn(Position) -> bool) -> Option<Path> {
+    let mut entered = [[None; MAZE_COLS]; MAZE_ROWS];
+    if start == end {
+        return Some(Path::trace(start, end, &entered));
+    }
+    if !on_maze(start) {
+        return None;
+    }
+
+    let heuristic = |pos: Position| pos.0.abs_diff(end.0) as u16 + pos.1.abs_diff(end.1) as u16;
+    let mut cost = [[u16::MAX; MAZE_COLS]; MAZE_ROWS];
+    let mut closed = [[false; MAZE_COLS]; MAZE_ROWS];
+    let mut open = [(0, 0); MAX_PATH_LEN];
+    cost[start.0 as usize][start.1 as usize] = 0;
+    open[0] = start;
+    let mut num_open = 1;
+
+    while num_open > 0 {
+        // Take the open cell with the lowest estimated total, preferring cells that are
+        // further along
+        let key = |pos: Position| {
+            let cost = cost[pos.0 as usize][pos.1 as usize];
+            (cost + heuristic(pos), u16::MAX - cost)
+        };
+        let best = (0..num_open).min_by_key(|&i| key(open[i])).unwrap();
+        let pos = open[best];
+        num_open -= 1;
+        open[best] = open[num_open];
+
+        if pos == end {
+            return Some(Path::trace(start, end, &entered));
+        }
+        closed[pos.0 as usize][pos.1 as usize] = true;
+        let next_cost = cost[pos.0 as usize][pos.1 as usize] + 1;
+
+        for dir in Direction::all_except_stay() {
+            let Some(next) = neighbor(pos, dir) else {
+                continue;
+            };
+            let (row, col) = (next.0 as usize, next.1 as usize);
+            if closed[row][col] || next_cost >= cost[row][col] || !walkable(next) {
+                continue;
+            }
+            if cost[row][col] == u16::MAX {
+                open[num_open] = next;
+                num_open += 1;
+            }
+            cost[row][col] = next_cost;
+            entered[row][col] = Some(dir);
+        }
+    }
+    None
+}
+
+impl GameState {
+    /// Finds a shortest path for Pacman between two cells with a breadth-first search,
+    /// without allocating (see [`bfs`]).
+    pub fn find_path(&self, start: Position, end: Position) -> Option<Path> {
+        bfs(start, end, |pos| !self.wall_at(pos))
+    }
+
+    /// Finds a shortest path for Pacman between two cells with A*, without allocating
+    /// (see [`astar`]).
+    pub fn find_path_astar(&self, start: Position, end: Position) -> Option<Path> {
+        astar(start, end, |pos| !self.wall_at(pos))
+    }
+}
@@ -0,0 +1,142 @@
+use array_init::array_init;
+use core::iter::{self, Empty};
+
+use crate::events::GameEvents;
+use crate::game_helpers::GhostPlan;
+use crate::game_state::GameState;
+use crate::location::{Direction, LocationState};
+
+/*
+Ghost prediction runs the engine's ghost logic on a copy of the game state, one update
+at a time: Pacman makes its move (if it has one), the ghosts move to their planned
+locations, the mode and fright timers count down, and the ghosts plan their next moves.
+The original state is never touched.
+
+Collisions are left out, so Pacman is never caught and frightened ghosts are never
+eaten: the prediction is of where the ghosts would go, for deciding whether to be
+there. Pellets that Pacman collects along the way still count, so a super pellet on
+its path frightens the ghosts in the prediction, as it would in the game.
+
+Frightened ghosts move at random. The copy uses the game's seed, so the prediction is
+exactly right if the real game shares the seed, but otherwise the locations of those
+ghosts are guesses, and they are marked as stochastic from then on.
+*/
+
+/// A ghost's predicted state after an update.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PredictedGhost {
+    /// The ghost's location (which is empty while it respawns)
+    pub loc: LocationState,
+    /// Whether the ghost is frightened
+    pub frightened: bool,
+    /// Whether the location depends on a random move, made while the ghost was
+    /// frightened
+    pub stochastic: bool,
+}
+
+/// The predicted locations of Pacman and the ghosts after an update.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PredictedStep {
+    /// The number of updates from now, starting at 1
+    pub step: u32,
+    /// Pacman's location
+    pub pacman: LocationState,
+    /// The ghosts, indexed by color
+    pub ghosts: [PredictedGhost; 4],
+}
+
+/// An iterator over the predicted locations of the ghosts, one update at a time,
+/// created by [`GameState::predict_ghosts`] or [`GameState::predict_ghosts_along`].
+#[derive(Clone, Debug)]
+pub struct GhostPrediction<I> {
+    state: GameState,
+    pacman_moves: I,
+    step: u32,
+    steps: u32,
+    stochastic: [bool; 4],
+}
+
+impl<I: Iterator<Item = Direction>> Iterator for GhostPrediction<I> {
+    type Item = PredictedStep;
+
+    fn next(&mut self) -> Option<PredictedStep> {
+        if self.step == self.steps {
+            return None;
+        }
+        self.step += 1;
+        let state = &mut self.state;
+        let mut events = GameEvents::new();
+
+        // Pacman moves first, without colliding with anything
+        if let Some(dir) = self.pacman_moves.next() {
+            let next_loc = state.pacman_loc.get_neighbor_coords(dir);
+            state.pacman_loc.dir = dir;
+            if !state.wall_at(next_loc) {
+                state.pacman_loc.update_coords(next_loc);
+                state.collect_pellet(next_loc, &mut events);
+            }
+        }
+
+        // Then the update, as in GameState::step
+        state.update_all_ghosts();
+        state.try_respawn_pacman();
+        state.handle_step_events(&mut events);
+
+        let ghosts = array_init(|i| {
+            let ghost = &state.ghosts[i];
+            PredictedGhost {
+                loc: ghost.loc,
+                frightened: ghost.is_frightened(),
+                stochastic: self.stochastic[i],
+            }
+        });
+        let plans = state.plan_ghosts();
+        for (stochastic, plan) in self.stochastic.iter_mut().zip(plans) {
+            *stochastic |= plan == GhostPlan::Random;
+        }
+
+        Some(PredictedStep {
+            step: self.step,
+            pacman: state.pacman_loc,
+            ghosts,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.steps - self.step) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<I: Iterator<Item = Direction>> ExactSizeIterator for GhostPrediction<I> {}
+
+impl GameState {
+    /// Predicts where the ghosts will be over the next `steps` updates, assuming that
+    /// Pacman stays where it is, without changing the game state.
+    pub fn predict_ghosts(&self, steps: u32) -> GhostPrediction<Empty<Direction>> {
+        self.predict_ghosts_along(steps, iter::empty())
+    }
+
+    /// Predicts where the ghosts will be over the next `steps` updates, assuming that
+    /// Pacman makes one move per update in the given directions (such as those of a
+    /// [`Path`](crate::path::Path)) and then stays where it is, without changing the game
+    /// state.
+    pub fn predict_ghosts_along<I: IntoIterator<Item = Direction>>(
+        &self,
+        steps: u32,
+        pacman_moves: I,
+    ) -> GhostPrediction<I::IntoIter> {
+        // A ghost that is frightened now may have planned its next move at random (unless
+        // it was only just frightened, and has yet to turn around)
+        let stochastic = self
+            .ghosts
+            .map(|ghost| ghost.fright_steps > 1 && !ghost.is_trapped());
+        GhostPrediction {
+            state: *self,
+            pacman_moves: pacman_moves.into_iter(),
+            step: 0,
+            steps,
+            stochastic,
+        }
+    }
+}
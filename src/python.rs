@@ -0,0 +1,302 @@
+//! Python bindings, built as the `pacbot_rs` module with maturin.
+//!
+//! Directions are passed as integers (0 = up, 1 = left, 2 = down, 3 = right, 4 = stay),
+//! and events are returned as their names (see [`GameEvent::name`]). Observations are
+//! numpy arrays of shape `(NUM_CHANNELS, MAZE_ROWS, MAZE_COLS)`, which are encoded
+//! directly into the array's memory.
+//!
+//! [`GameEvent::name`]: crate::events::GameEvent::name
+
+use numpy::{PyArray1, PyArray3, PyArrayMethods, PyReadwriteArray3};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use crate::config::GameConfig;
+use crate::env::{EnvConfig, PacmanEnv, Reward, ScoreDelta};
+use crate::events::GameEvents;
+use crate::game_state::GameState;
+use crate::location::{Direction, InvalidDirection};
+use crate::obs::{NUM_CHANNELS, OBS_LEN};
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+impl From<InvalidDirection> for PyErr {
+    fn from(e: InvalidDirection) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+// Encode an observation into a new array, without copying
+fn obs_array<'py>(py: Python<'py>, state: &GameState) -> Bound<'py, PyArray3<f32>> {
+    let array = PyArray3::zeros(py, [NUM_CHANNELS, MAZE_ROWS, MAZE_COLS], false);
+    // Safety: the array was just created, so nothing else can be accessing it
+    let buf: &mut [f32; OBS_LEN] = unsafe { array.as_slice_mut() }.unwrap().try_into().unwrap();
+    state.encode_obs(buf);
+    array
+}
+
+/// The state of a game.
+#[pyclass(name = "GameState", module = "pacbot_rs")]
+#[derive(Clone)]
+pub struct PyGameState(pub GameState);
+
+#[pymethods]
+impl PyGameState {
+    /// Creates a new game, with a random seed if none is given
+    #[new]
+    #[pyo3(signature = (seed=None))]
+    fn new(seed: Option<u64>) -> Self {
+        Self(match seed {
+            Some(seed) => GameState::new_with_seed(seed),
+            None => GameState::new(),
+        })
+    }
+
+    /// Decodes a game state in the same format as the server
+    #[staticmethod]
+    #[pyo3(signature = (bytes, seed=0))]
+    fn from_bytes(bytes: &[u8], seed: u64) -> PyResult<Self> {
+        GameState::from_bytes(bytes, seed)
+            .map(Self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Encodes the game state in the same format as the server
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.to_packet())
+    }
+
+    fn clone(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __copy__(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        Clone::clone(self)
+    }
+
+    /// Advances the game by one tick, returning the events that happened
+    fn step(&mut self) -> Vec<String> {
+        self.0.step().names()
+    }
+
+    /// Moves Pacman in a direction, returning the events that happened
+    fn move_pacman_dir(&mut self, dir: u8) -> PyResult<Vec<String>> {
+        let dir = Direction::try_from(dir)?;
+        Ok(self.0.move_pacman_dir(dir).names())
+    }
+
+    /// Moves Pacman, then advances to the next update, returning the events that
+    /// happened and whether the game is over
+    fn step_with_action(&mut self, dir: u8) -> PyResult<(Vec<String>, bool)> {
+        let result = self.0.step_with_action(Direction::try_from(dir)?);
+        Ok((result.events.names(), result.game_over))
+    }
+
+    /// Sets Pacman's location, returning the events that happened along the way, or
+    /// raising a ValueError if Pacman can't be moved there
+    fn set_pacman_location(&mut self, row: i8, col: i8) -> PyResult<Vec<String>> {
+        self.0
+            .set_pacman_location((row, col))
+            .map(|events| events.names())
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Encodes the game state as planes for a neural network
+    fn obs<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray3<f32>> {
+        obs_array(py, &self.0)
+    }
+
+    /// Encodes the game state as planes for a neural network into an existing array
+    fn obs_into(&self, mut out: PyReadwriteArray3<'_, f32>) -> PyResult<()> {
+        let buf: &mut [f32; OBS_LEN] = out
+            .as_slice_mut()
+            .ok()
+            .and_then(|buf| buf.try_into().ok())
+            .ok_or_else(|| {
+                PyValueError::new_err("expected a contiguous array of the right size")
+            })?;
+        self.0.encode_obs(buf);
+        Ok(())
+    }
+
+    #[getter]
+    fn ticks(&self) -> u32 {
+        self.0.curr_ticks
+    }
+
+    #[getter]
+    fn update_period(&self) -> u8 {
+        self.0.update_period
+    }
+
+    #[getter]
+    fn paused(&self) -> bool {
+        self.0.paused
+    }
+
+    #[setter]
+    fn set_paused(&mut self, paused: bool) {
+        self.0.paused = paused;
+    }
+
+    #[getter]
+    fn score(&self) -> u16 {
+        self.0.curr_score
+    }
+
+    #[getter]
+    fn level(&self) -> u8 {
+        self.0.curr_level
+    }
+
+    #[getter]
+    fn lives(&self) -> u8 {
+        self.0.curr_lives
+    }
+
+    #[getter]
+    fn num_pellets(&self) -> u16 {
+        self.0.num_pellets
+    }
+
+    #[getter]
+    fn pacman_loc(&self) -> (i8, i8) {
+        self.0.pacman_loc.get_coords()
+    }
+
+    #[getter]
+    fn ghost_locs(&self) -> Vec<(i8, i8)> {
+        self.0.ghosts.iter().map(|g| g.loc.get_coords()).collect()
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.0.is_game_over()
+    }
+}
+
+// A reward given by a Python function of the previous and next states, or the change in
+// score if there is none
+struct PyReward {
+    callback: Option<Py<PyAny>>,
+    error: Option<PyErr>,
+}
+
+impl Reward for PyReward {
+    fn reward(&mut self, prev: &GameState, next: &GameState, events: &GameEvents) -> f32 {
+        let Some(callback) = &self.callback else {
+            return ScoreDelta.reward(prev, next, events);
+        };
+        let result = Python::attach(|py| {
+            let args = (PyGameState(*prev), PyGameState(*next));
+            callback.call1(py, args)?.extract(py)
+        });
+        result.unwrap_or_else(|e| {
+            self.error = Some(e);
+            0.0
+        })
+    }
+}
+
+/// A reinforcement learning environment, in the style of Gym.
+#[pyclass(name = "Env", module = "pacbot_rs")]
+pub struct PyEnv(PacmanEnv<PyReward>);
+
+impl PyEnv {
+    fn info<'py>(
+        &self,
+        py: Python<'py>,
+        events: &GameEvents,
+        ticks: u32,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let info = PyDict::new(py);
+        info.set_item("events", events.names())?;
+        info.set_item("ticks", ticks)?;
+        info.set_item("steps", self.0.steps())?;
+        info.set_item("score", self.0.state().curr_score)?;
+        info.set_item("lives", self.0.state().curr_lives)?;
+        Ok(info)
+    }
+}
+
+#[pymethods]
+impl PyEnv {
+    /// Creates an environment; `reward`, if given, is called with the previous and next
+    /// states, and returns the reward
+    #[new]
+    #[pyo3(signature = (max_steps=None, reward=None))]
+    fn new(max_steps: Option<u32>, reward: Option<Py<PyAny>>) -> Self {
+        let config = EnvConfig {
+            game: GameConfig::default(),
+            max_steps,
+        };
+        let reward = PyReward {
+            callback: reward,
+            error: None,
+        };
+        Self(PacmanEnv::with_reward(config, reward))
+    }
+
+    /// Starts a new episode, returning the observation and info
+    fn reset<'py>(
+        &mut self,
+        py: Python<'py>,
+        seed: u64,
+    ) -> PyResult<(Bound<'py, PyArray3<f32>>, Bound<'py, PyDict>)> {
+        let (state, info) = self.0.reset(seed);
+        Ok((obs_array(py, &state), self.info(py, &info.events, 0)?))
+    }
+
+    /// Takes a step, returning the observation, reward, whether the episode terminated,
+    /// whether it was truncated, and info
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: u8,
+    ) -> PyResult<(
+        Bound<'py, PyArray3<f32>>,
+        f32,
+        bool,
+        bool,
+        Bound<'py, PyDict>,
+    )> {
+        let action = Direction::try_from(action)?;
+        let (state, reward, terminated, truncated, info) = self.0.step(action);
+        if let Some(e) = self.0.reward_mut().error.take() {
+            return Err(e);
+        }
+        let obs = obs_array(py, &state);
+        Ok((
+            obs,
+            reward,
+            terminated,
+            truncated,
+            self.info(py, &info.events, info.ticks)?,
+        ))
+    }
+
+    /// Which actions would move Pacman, indexed by action
+    fn action_mask<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<bool>> {
+        PyArray1::from_slice(py, &self.0.action_mask())
+    }
+
+    /// The current state of the game
+    #[getter]
+    fn state(&self) -> PyGameState {
+        PyGameState(*self.0.state())
+    }
+}
+
+#[pymodule]
+fn pacbot_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGameState>()?;
+    m.add_class::<PyEnv>()?;
+    m.add("NUM_CHANNELS", NUM_CHANNELS)?;
+    m.add("MAZE_ROWS", MAZE_ROWS)?;
+    m.add("MAZE_COLS", MAZE_COLS)?;
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+
+use crate::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::config::GameConfig;
+use crate::events::GameEvents;
+use crate::game_state::{GameState, LocationError, LocationPolicy};
+use crate::location::Direction;
+use crate::snapshot::{read_config, write_config, CONFIG_LEN};
+
+/// The bytes that every encoded replay starts with
+pub const REPLAY_MAGIC: [u8; 4] = *b"PBRP";
+
+/// The current version of the replay format
+pub const REPLAY_VERSION: u8 = 2;
+
+// Magic, version, seed, config, end tick and input count
+const HEADER_LEN: usize = 4 + 1 + 8 + CONFIG_LEN + 4 + 4;
+
+// Tick, kind and four bytes of payload
+const INPUT_LEN: usize = 4 + 1 + 4;
+
+/*
+A replay is the seed and rules that a game started with, followed by every input that
+was given to it, each tagged with the tick that it was given on. Since the engine is
+deterministic, stepping a new game and applying each input on the same tick reproduces
+the original game exactly.
+*/
+
+/// Something that was done to the game state, other than stepping it.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReplayInput {
+    /// Pacman moved with [`GameState::move_pacman_dir`]
+    Move(Direction),
+    /// Pacman's location was set with [`GameState::set_pacman_location_with`]
+    SetLocation {
+        row: i8,
+        col: i8,
+        policy: LocationPolicy,
+    },
+    /// The game was paused or unpaused
+    SetPaused(bool),
+}
+
+impl ReplayInput {
+    /// Applies the input to the game state, returning the events that happened, or an
+    /// error (leaving the game unchanged) if it sets a location that Pacman can't be
+    /// moved to.
+    pub fn apply(&self, state: &mut GameState) -> Result<GameEvents, LocationError> {
+        match *self {
+            ReplayInput::Move(dir) => Ok(state.move_pacman_dir(dir)),
+            ReplayInput::SetLocation { row, col, policy } => {
+                state.set_pacman_location_with((row, col), policy)
+            }
+            ReplayInput::SetPaused(paused) => {
+                state.paused = paused;
+                Ok(GameEvents::new())
+            }
+        }
+    }
+}
+
+/// An input, tagged with the tick that it was given on.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TickInput {
+    pub tick: u32,
+    pub input: ReplayInput,
+}
+
+/// A recording of a game, which can be played back exactly with a [`Replayer`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Replay {
+    /// The seed that the game started with
+    pub seed: u64,
+    /// The rules that the game was played with
+    pub config: GameConfig,
+    /// Every input given to the game, in order
+    pub inputs: Vec<TickInput>,
+    /// The tick that the recording ends on
+    pub end_tick: u32,
+}
+
+impl Replay {
+    /// Creates an empty replay of a game that starts with the given seed and rules.
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        Self {
+            seed,
+            config,
+            inputs: Vec::new(),
+            end_tick: 0,
+        }
+    }
+
+    /// The state that the game starts in
+    pub fn initial_state(&self) -> GameState {
+        GameState::new_with_config(self.seed, self.config)
+    }
+
+    /// Plays back the replay from the start
+    pub fn replayer(&self) -> Replayer<'_> {
+        Replayer::new(self)
+    }
+
+    /// Encodes the replay.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; HEADER_LEN + self.inputs.len() * INPUT_LEN];
+        let mut w = ByteWriter::new(&mut buf);
+
+        // Header
+        w.bytes(&REPLAY_MAGIC);
+        w.u8(REPLAY_VERSION);
+        w.u64(self.seed);
+        write_config(&mut w, &self.config);
+        w.u32(self.end_tick);
+        w.u32(self.inputs.len() as u32);
+
+        // Inputs
+        for TickInput { tick, input } in &self.inputs {
+            w.u32(*tick);
+            match *input {
+                ReplayInput::Move(dir) => w.bytes(&[0, dir as u8, 0, 0, 0]),
+                ReplayInput::SetLocation { row, col, policy } => {
+                    // Each policy is its own kind, followed by the location and then the
+                    // maximum distance, if any
+                    let (kind, max_distance) = match policy {
+                        LocationPolicy::ReplayPath => (1, 0),
+                        LocationPolicy::Teleport => (3, 0),
+                        LocationPolicy::MaxDistance(max_distance) => (4, max_distance),
+                    };
+                    w.bytes(&[kind, row as u8, col as u8]);
+                    w.u16(max_distance);
+                }
+                ReplayInput::SetPaused(paused) => w.bytes(&[2, paused as u8, 0, 0, 0]),
+            }
+        }
+
+        debug_assert_eq!(w.offset(), buf.len());
+        buf
+    }
+
+    /// Decodes a replay made by [`Replay::to_bytes`].
+    ///
+    /// The replay's maze must be the standard maze or a registered maze (see
+    /// [`Maze::register`](crate::maze::Maze::register)).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut r = ByteReader::new(bytes);
+
+        // Header
+        let offset = r.offset();
+        let magic: [u8; 4] = r.bytes("magic")?;
+        if let Some(i) = (0..4).find(|&i| magic[i] != REPLAY_MAGIC[i]) {
+            return Err(DecodeError::InvalidValue {
+                field: "magic",
+                offset: offset + i,
+                value: magic[i],
+            });
+        }
+        let version = r.u8("version")?;
+        if version != REPLAY_VERSION {
+            return Err(DecodeError::UnsupportedVersion { version });
+        }
+        let seed = r.u64("seed")?;
+        let config = read_config(&mut r, &[])?;
+        let end_tick = r.u32("end_tick")?;
+        let num_inputs = r.u32("input count")? as usize;
+
+        // Check the length up front, so that a corrupt count can't allocate too much
+        let expected = HEADER_LEN.saturating_add(num_inputs.saturating_mul(INPUT_LEN));
+        if bytes.len() != expected {
+            return Err(DecodeError::WrongLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        // Inputs
+        let mut inputs = Vec::with_capacity(num_inputs);
+        for _ in 0..num_inputs {
+            let tick = r.u32("input tick")?;
+            let kind = r.u8("input kind")?;
+            let input = match kind {
+                0 => {
+                    let dir = r.parse("input direction", |x| x.try_into().ok())?;
+                    r.bytes::<3>("input padding")?;
+                    ReplayInput::Move(dir)
+                }
+                1 | 3 | 4 => {
+                    let row = r.i8("input location")?;
+                    let col = r.i8("input location")?;
+                    let max_distance = r.u16("input max distance")?;
+                    let policy = match kind {
+                        1 => LocationPolicy::ReplayPath,
+                        3 => LocationPolicy::Teleport,
+                        _ => LocationPolicy::MaxDistance(max_distance),
+                    };
+                    ReplayInput::SetLocation { row, col, policy }
+                }
+                2 => {
+                    let paused = r.parse("input paused", |x| match x {
+                        0 => Some(false),
+                        1 => Some(true),
+                        _ => None,
+                    })?;
+                    r.bytes::<3>("input padding")?;
+                    ReplayInput::SetPaused(paused)
+                }
+                _ => {
+                    return Err(DecodeError::InvalidValue {
+                        field: "input kind",
+                        offset: r.offset() - 1,
+                        value: kind,
+                    })
+                }
+            };
+            inputs.push(TickInput { tick, input });
+        }
+
+        Ok(Self {
+            seed,
+            config,
+            inputs,
+            end_tick,
+        })
+    }
+}
+
+/// Plays a game while recording it, by standing in for the [`GameState`].
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    state: GameState,
+    replay: Replay,
+}
+
+impl Recorder {
+    /// Starts recording a new game with the given seed and rules.
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        let replay = Replay::new(seed, config);
+        Self {
+            state: replay.initial_state(),
+            replay,
+        }
+    }
+
+    /// The current state of the game
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The recording so far
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Stops recording, returning the replay
+    pub fn into_replay(self) -> Replay {
+        self.replay
+    }
+
+    // Record an input on the current tick
+    fn record(&mut self, input: ReplayInput) {
+        self.replay.inputs.push(TickInput {
+            tick: self.state.curr_ticks,
+            input,
+        });
+    }
+
+    /// Applies an input to the game and records it (see [`ReplayInput::apply`]).
+    ///
+    /// Inputs that fail are recorded too, since they fail the same way when replayed.
+    pub fn apply(&mut self, input: ReplayInput) -> Result<GameEvents, LocationError> {
+        self.record(input);
+        input.apply(&mut self.state)
+    }
+
+    /// Moves Pacman, as with [`GameState::move_pacman_dir`], and records it.
+    pub fn move_pacman_dir(&mut self, dir: Direction) -> GameEvents {
+        self.record(ReplayInput::Move(dir));
+        self.state.move_pacman_dir(dir)
+    }
+
+    /// Sets Pacman's location, as with [`GameState::set_pacman_location`], and records it.
+    pub fn set_pacman_location(&mut self, location: (i8, i8)) -> Result<GameEvents, LocationError> {
+        self.set_pacman_location_with(location, LocationPolicy::ReplayPath)
+    }
+
+    /// Sets Pacman's location, as with [`GameState::set_pacman_location_with`], and
+    /// records it along with the policy.
+    pub fn set_pacman_location_with(
+        &mut self,
+        (row, col): (i8, i8),
+        policy: LocationPolicy,
+    ) -> Result<GameEvents, LocationError> {
+        self.apply(ReplayInput::SetLocation { row, col, policy })
+    }
+
+    /// Pauses or unpauses the game, and records it.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.record(ReplayInput::SetPaused(paused));
+        self.state.paused = paused;
+    }
+
+    /// Steps the game, as with [`GameState::step`], and records it.
+    pub fn step(&mut self) -> GameEvents {
+        let events = self.state.step();
+        self.replay.end_tick = self.state.curr_ticks;
+        events
+    }
+}
+
+/// Plays back a [`Replay`] one tick at a time.
+///
+/// On each tick, every input recorded on that tick is applied, in order. Inputs recorded
+/// on an earlier tick than the current one (which a valid replay never has) are applied
+/// as soon as they are reached.
+#[derive(Clone, Debug)]
+pub struct Replayer<'a> {
+    replay: &'a Replay,
+    state: GameState,
+    next_input: usize,
+    events: GameEvents,
+}
+
+impl<'a> Replayer<'a> {
+    /// Starts playing back the replay, applying any inputs from the first tick.
+    pub fn new(replay: &'a Replay) -> Self {
+        let mut replayer = Self {
+            replay,
+            state: replay.initial_state(),
+            next_input: 0,
+            events: GameEvents::new(),
+        };
+        replayer.events = replayer.apply_inputs();
+        replayer
+    }
+
+    // Apply every input up to the current tick
+    fn apply_inputs(&mut self) -> GameEvents {
+        let mut events = GameEvents::new();
+        while let Some(TickInput { tick, input }) = self.replay.inputs.get(self.next_input) {
+            if *tick > self.state.curr_ticks {
+                break;
+            }
+            // Inputs that failed when they were recorded fail again, changing nothing
+            if let Ok(input_events) = input.apply(&mut self.state) {
+                events.extend(&input_events);
+            }
+            self.next_input += 1;
+        }
+        events
+    }
+
+    /// The state of the game on the current tick, after that tick's inputs
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The events that happened on the current tick
+    pub fn events(&self) -> &GameEvents {
+        &self.events
+    }
+
+    /// Whether the end of the replay has been reached
+    pub fn is_finished(&self) -> bool {
+        self.state.curr_ticks >= self.replay.end_tick
+    }
+
+    /// Steps to the next tick and applies its inputs, returning the events that happened,
+    /// or `None` if the end of the replay has been reached.
+    pub fn advance(&mut self) -> Option<&GameEvents> {
+        if self.is_finished() {
+            return None;
+        }
+        self.events = self.state.step();
+        let input_events = self.apply_inputs();
+        self.events.extend(&input_events);
+        Some(&self.events)
+    }
+
+    /// Advances until the given tick, or the end of the replay, returning the state there.
+    pub fn seek(&mut self, tick: u32) -> &GameState {
+        while self.state.curr_ticks < tick && self.advance().is_some() {}
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn decoded_replay_reproduces_recording() {
+        let mut recorder = Recorder::new(3, GameConfig::default());
+        recorder.set_paused(false);
+        let mut rng = SmallRng::seed_from_u64(4);
+        let mut checkpoints = Vec::new();
+        for tick in 0..3000 {
+            if recorder.state().update_ready() {
+                let dir: u8 = rng.gen_range(0..5);
+                recorder.move_pacman_dir(Direction::try_from(dir).unwrap());
+            }
+            // Set locations with each policy, which give different results for far away
+            // locations, and so must be replayed with the same policy (inputs that fail,
+            // such as while Pacman is dead, are recorded and replayed too)
+            let spawn = recorder.state().config.maze.pacman_spawn_loc.get_coords();
+            let _ = match tick % 700 {
+                100 => recorder.set_pacman_location_with((1, 1), LocationPolicy::Teleport),
+                350 => recorder.set_pacman_location(spawn),
+                600 => recorder.set_pacman_location_with(spawn, LocationPolicy::MaxDistance(10)),
+                _ => Ok(GameEvents::new()),
+            };
+            if tick % 500 == 0 {
+                checkpoints.push(*recorder.state());
+            }
+            recorder.step();
+        }
+        let last = *recorder.state();
+        assert!(!last.is_game_over());
+        let replay = recorder.into_replay();
+        for policy in [
+            LocationPolicy::ReplayPath,
+            LocationPolicy::Teleport,
+            LocationPolicy::MaxDistance(10),
+        ] {
+            assert!(replay.inputs.iter().any(|input| matches!(
+                input.input,
+                ReplayInput::SetLocation { policy: p, .. } if p == policy
+            )));
+        }
+
+        let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(decoded, replay);
+
+        let mut replayer = decoded.replayer();
+        for state in checkpoints {
+            assert_eq!(*replayer.seek(state.curr_ticks), state);
+        }
+        assert_eq!(*replayer.seek(u32::MAX), last);
+        assert!(replayer.is_finished());
+    }
+}
@@ -0,0 +1,6 @@
+/*
+Planners that look ahead by playing the game forward on copies of the game state, so
+that they follow exactly the same rules as the engine.
+*/
+
+pub mod mcts;
@@ -0,0 +1,494 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+use crate::env::action_mask;
+use crate::game_state::GameState;
+use crate::location::Direction;
+
+/*
+Monte Carlo Tree Search over Pacman's moves. Each move in the tree is made on a copy
+of the game state with GameState::step_with_action (which is move_pacman_dir followed
+by step until the next update), so the search plays by the engine's rules and nothing
+else. Moves into walls are left out, since they are the same as staying.
+
+Frightened ghosts move at random, using the game's seed. The real game's seed is
+usually unknown, so by default every move in the search is made with a new seed drawn
+from the search's own generator. The state after a move is then a chance node: each
+move keeps the different states that it has led to (compared without the seed), up to
+a limit, and once the limit is reached, later visits go to one of those states, picked
+in proportion to how often it has come up. When no ghost is frightened, every seed
+gives the same state, and each move has a single outcome.
+
+Moves are picked with UCT, on values scaled between the lowest and highest returns
+seen so far, so that the exploration constant does not depend on the size of the
+rewards. Past the tree, a rollout policy plays on for a fixed number of moves. The
+reward for a move is the change in score, less a penalty for each life lost.
+
+All randomness comes from a generator seeded from the config, so a search with a
+budget of iterations always gives the same result for the same state and config.
+*/
+
+/// How long a search runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Budget {
+    /// Run this many iterations
+    Iterations(u32),
+    /// Run iterations until this much time has passed (always running at least one),
+    /// which is not deterministic, since the number of iterations depends on the machine
+    Time(Duration),
+}
+
+/// How a search treats the random moves of frightened ghosts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Chance {
+    /// Use the game's own seed, which is exact when the real game shares it
+    KnownSeed,
+    /// Make each move with a new seed, keeping up to this many different outcomes of
+    /// each move in the tree
+    Sample { max_outcomes: u8 },
+}
+
+impl Default for Chance {
+    fn default() -> Self {
+        Chance::Sample { max_outcomes: 4 }
+    }
+}
+
+/// The settings of an [`Mcts`] search.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MctsConfig {
+    /// How long to search for
+    pub budget: Budget,
+    /// The seed of the search's random number generator
+    pub seed: u64,
+    /// How to treat the random moves of frightened ghosts
+    pub chance: Chance,
+    /// The UCT exploration constant, for values scaled between 0 and 1
+    pub exploration: f32,
+    /// The number of moves that each rollout makes beyond the tree
+    pub rollout_depth: u32,
+    /// How much less each reward counts than the one before it
+    pub discount: f32,
+    /// The penalty for each life that Pacman loses
+    pub life_penalty: f32,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            budget: Budget::Iterations(1000),
+            seed: 0,
+            chance: Chance::default(),
+            exploration: 1.4,
+            rollout_depth: 30,
+            discount: 0.99,
+            life_penalty: 500.0,
+        }
+    }
+}
+
+/// Chooses Pacman's moves during the rollouts of an [`Mcts`] search.
+pub trait RolloutPolicy {
+    /// The move to make from a state, which is not game over, using the search's random
+    /// number generator for any randomness (so that searches stay deterministic)
+    fn choose(&mut self, state: &GameState, rng: &mut SmallRng) -> Direction;
+}
+
+impl<F: FnMut(&GameState, &mut SmallRng) -> Direction> RolloutPolicy for F {
+    fn choose(&mut self, state: &GameState, rng: &mut SmallRng) -> Direction {
+        self(state, rng)
+    }
+}
+
+/// The default rollout policy, which moves at random but never into a wall, and only
+/// stays when it can't move.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RandomRollout;
+
+impl RolloutPolicy for RandomRollout {
+    fn choose(&mut self, state: &GameState, rng: &mut SmallRng) -> Direction {
+        let mask = action_mask(state);
+        random_move(rng, |dir| mask[dir as usize])
+    }
+}
+
+/// A rollout policy that keeps moving the way Pacman is facing, turns at random at
+/// corners and junctions, and only turns back at dead ends.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ForwardRollout;
+
+impl RolloutPolicy for ForwardRollout {
+    fn choose(&mut self, state: &GameState, rng: &mut SmallRng) -> Direction {
+        let mask = action_mask(state);
+        let back = state.pacman_loc.dir.opposite();
+        let dir = random_move(rng, |dir| mask[dir as usize] && dir != back);
+        if dir == Direction::Stay && back != Direction::Stay && mask[back as usize] {
+            return back;
+        }
+        dir
+    }
+}
+
+// Pick one of the moves (other than staying) that are allowed, or stay if none are
+fn random_move(rng: &mut SmallRng, allowed: impl Fn(Direction) -> bool) -> Direction {
+    let mut moves = [Direction::Stay; 4];
+    let mut num_moves = 0;
+    for dir in Direction::all_except_stay() {
+        if allowed(dir) {
+            moves[num_moves] = dir;
+            num_moves += 1;
+        }
+    }
+    if num_moves == 0 {
+        return Direction::Stay;
+    }
+    moves[rng.gen_range(0..num_moves)]
+}
+
+/// The statistics of one of Pacman's moves at the root of a search.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveStats {
+    /// The move
+    pub dir: Direction,
+    /// The number of iterations that started with the move
+    pub visits: u32,
+    /// The mean discounted return of those iterations
+    pub value: f32,
+}
+
+/// The outcome of an [`Mcts`] search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// The move that was tried the most, or [`Direction::Stay`] if the game is over
+    pub best: Direction,
+    /// The number of iterations that were run
+    pub iterations: u32,
+    /// The statistics of every move that Pacman could make
+    pub moves: Vec<MoveStats>,
+}
+
+// A state in the tree, where Pacman chooses a move
+#[derive(Clone, Debug)]
+struct Node {
+    state: GameState,
+    visits: u32,
+    edges: Vec<Edge>,
+}
+
+// A move from a node, and the chance node of states that it has led to
+#[derive(Clone, Debug)]
+struct Edge {
+    dir: Direction,
+    visits: u32,
+    total: f32,
+    outcomes: Vec<Outcome>,
+}
+
+// A state that a move has led to, and the reward for getting there
+#[derive(Copy, Clone, Debug)]
+struct Outcome {
+    node: usize,
+    reward: f32,
+    visits: u32,
+}
+
+// The nodes of a search, and the range of the returns seen so far
+#[derive(Clone, Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+    min_value: f32,
+    max_value: f32,
+}
+
+impl Tree {
+    // Add a node for a state, with an edge for each of Pacman's moves (or none, if the
+    // game is over)
+    fn add_node(&mut self, state: GameState) -> usize {
+        let mut edges = Vec::new();
+        if !state.is_game_over() {
+            let mask = action_mask(&state);
+            for (i, _) in mask.iter().enumerate().filter(|(_, &allowed)| allowed) {
+                edges.push(Edge {
+                    dir: (i as u8).try_into().unwrap(),
+                    visits: 0,
+                    total: 0.0,
+                    outcomes: Vec::new(),
+                });
+            }
+        }
+        self.nodes.push(Node {
+            state,
+            visits: 0,
+            edges,
+        });
+        self.nodes.len() - 1
+    }
+
+    // Scale a value between the lowest and highest returns seen so far
+    fn normalize(&self, value: f32) -> f32 {
+        if self.max_value > self.min_value {
+            (value - self.min_value) / (self.max_value - self.min_value)
+        } else {
+            0.5
+        }
+    }
+}
+
+/// A Monte Carlo Tree Search planner for Pacman, with a configurable rollout policy.
+#[derive(Clone, Debug)]
+pub struct Mcts<P = RandomRollout> {
+    config: MctsConfig,
+    policy: P,
+}
+
+impl Mcts {
+    /// Creates a planner with the default rollout policy, [`RandomRollout`].
+    pub fn new(config: MctsConfig) -> Self {
+        Self::with_policy(config, RandomRollout)
+    }
+}
+
+impl<P: RolloutPolicy> Mcts<P> {
+    /// Creates a planner with a custom rollout policy.
+    pub fn with_policy(config: MctsConfig, policy: P) -> Self {
+        Self { config, policy }
+    }
+
+    /// The settings of the search
+    pub fn config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    /// The settings of the search, mutably
+    pub fn config_mut(&mut self) -> &mut MctsConfig {
+        &mut self.config
+    }
+
+    /// The rollout policy
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// The rollout policy, mutably
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// Searches for Pacman's best move from a state, without changing the state.
+    pub fn search(&mut self, state: &GameState) -> SearchResult {
+        let mut rng = SmallRng::seed_from_u64(self.config.seed);
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            min_value: f32::INFINITY,
+            max_value: f32::NEG_INFINITY,
+        };
+        tree.add_node(*state);
+
+        let mut iterations = 0;
+        if !tree.nodes[0].edges.is_empty() {
+            // Only time budgets read the clock, which isn't available on every target
+            let (max_iterations, deadline) = match self.config.budget {
+                Budget::Iterations(max) => (max, None),
+                Budget::Time(time) => (u32::MAX, Some(Instant::now() + time)),
+            };
+            let mut path = Vec::new();
+            while iterations < max_iterations {
+                if iterations > 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+                self.iterate(&mut tree, &mut path, &mut rng);
+                iterations += 1;
+            }
+        }
+
+        let moves: Vec<_> = tree.nodes[0]
+            .edges
+            .iter()
+            .map(|edge| MoveStats {
+                dir: edge.dir,
+                visits: edge.visits,
+                value: if edge.visits > 0 {
+                    edge.total / edge.visits as f32
+                } else {
+                    0.0
+                },
+            })
+            .collect();
+        // The most visited move, preferring the higher value and then the earlier move
+        // (values are compared totally, since a config with NaN in it gives NaN values)
+        let best = moves
+            .iter()
+            .rev()
+            .max_by(|a, b| a.visits.cmp(&b.visits).then(a.value.total_cmp(&b.value)))
+            .map_or(Direction::Stay, |stats| stats.dir);
+        SearchResult {
+            best,
+            iterations,
+            moves,
+        }
+    }
+
+    /// Searches for Pacman's best move from a state (see [`Mcts::search`]).
+    pub fn best_direction(&mut self, state: &GameState) -> Direction {
+        self.search(state).best
+    }
+
+    // Whether each move is made with a new seed
+    fn sample_seeds(&self) -> bool {
+        matches!(self.config.chance, Chance::Sample { .. })
+    }
+
+    // The reward for a move from one state to another
+    fn reward(&self, prev: &GameState, next: &GameState) -> f32 {
+        let lives_lost = prev.curr_lives.saturating_sub(next.curr_lives);
+        next.curr_score as f32
+            - prev.curr_score as f32
+            - self.config.life_penalty * lives_lost as f32
+    }
+
+    // Pick the move to try from a node: any move that hasn't been tried yet, in order,
+    // and otherwise the move with the highest upper confidence bound
+    fn select(&self, tree: &Tree, node: usize) -> usize {
+        let node = &tree.nodes[node];
+        if let Some(i) = node.edges.iter().position(|edge| edge.visits == 0) {
+            return i;
+        }
+        let log_visits = (node.visits as f32).ln();
+        let bound = |edge: &Edge| {
+            let value = tree.normalize(edge.total / edge.visits as f32);
+            value + self.config.exploration * (log_visits / edge.visits as f32).sqrt()
+        };
+        let mut best = 0;
+        for (i, edge) in node.edges.iter().enumerate().skip(1) {
+            if bound(edge) > bound(&node.edges[best]) {
+                best = i;
+            }
+        }
+        best
+    }
+
+    // Make a move from a node, returning the outcome that it led to and whether that
+    // outcome is new to the tree
+    fn transition(
+        &self,
+        tree: &mut Tree,
+        node: usize,
+        edge: usize,
+        rng: &mut SmallRng,
+    ) -> (usize, bool) {
+        let prev = tree.nodes[node].state;
+        let dir = tree.nodes[node].edges[edge].dir;
+        let mut next = prev;
+        if self.sample_seeds() {
+            next.seed = rng.gen();
+        }
+        next.step_with_action(dir);
+        let reward = self.reward(&prev, &next);
+        if self.sample_seeds() {
+            next.seed = 0;
+        }
+
+        let outcomes = &tree.nodes[node].edges[edge].outcomes;
+        if let Some(i) = outcomes
+            .iter()
+            .position(|outcome| tree.nodes[outcome.node].state == next)
+        {
+            return (i, false);
+        }
+        let max_outcomes = match self.config.chance {
+            Chance::KnownSeed => 1,
+            Chance::Sample { max_outcomes } => max_outcomes.max(1) as usize,
+        };
+        if outcomes.len() >= max_outcomes {
+            // Revisit one of the known outcomes, in proportion to how often it has come up
+            let total: u32 = outcomes.iter().map(|outcome| outcome.visits).sum();
+            let mut pick = rng.gen_range(0..total);
+            for (i, outcome) in outcomes.iter().enumerate() {
+                if pick < outcome.visits {
+                    return (i, false);
+                }
+                pick -= outcome.visits;
+            }
+            unreachable!();
+        }
+
+        let child = tree.add_node(next);
+        let outcomes = &mut tree.nodes[node].edges[edge].outcomes;
+        outcomes.push(Outcome {
+            node: child,
+            reward,
+            visits: 0,
+        });
+        (outcomes.len() - 1, true)
+    }
+
+    // Play on from a state with the rollout policy, returning the discounted return
+    fn rollout(&mut self, state: &GameState, rng: &mut SmallRng) -> f32 {
+        let mut state = *state;
+        if self.sample_seeds() {
+            state.seed = rng.gen();
+        }
+        let mut value = 0.0;
+        let mut weight = 1.0;
+        for _ in 0..self.config.rollout_depth {
+            if state.is_game_over() {
+                break;
+            }
+            let prev = state;
+            let dir = self.policy.choose(&state, rng);
+            state.step_with_action(dir);
+            value += weight * self.reward(&prev, &state);
+            weight *= self.config.discount;
+        }
+        value
+    }
+
+    // Run one iteration: walk down the tree, add a node and roll out from it, then pass
+    // the return back up the path that was taken
+    fn iterate(
+        &mut self,
+        tree: &mut Tree,
+        path: &mut Vec<(usize, usize, usize)>,
+        rng: &mut SmallRng,
+    ) {
+        path.clear();
+        let mut node = 0;
+        let mut value = 0.0;
+        while !tree.nodes[node].edges.is_empty() {
+            let edge = self.select(tree, node);
+            let (outcome, new) = self.transition(tree, node, edge, rng);
+            path.push((node, edge, outcome));
+            node = tree.nodes[node].edges[edge].outcomes[outcome].node;
+            if new {
+                let state = tree.nodes[node].state;
+                value = self.rollout(&state, rng);
+                break;
+            }
+        }
+
+        for &(node, edge, outcome) in path.iter().rev() {
+            let node = &mut tree.nodes[node];
+            let edge = &mut node.edges[edge];
+            let outcome = &mut edge.outcomes[outcome];
+            value = outcome.reward + self.config.discount * value;
+            outcome.visits += 1;
+            edge.visits += 1;
+            edge.total += value;
+            node.visits += 1;
+            tree.min_value = tree.min_value.min(value);
+            tree.max_value = tree.max_value.max(value);
+        }
+    }
+}
+
+/// Searches for Pacman's best move from a state with the default settings and rollout
+/// policy, but the given budget and seed (see [`Mcts::search`]).
+pub fn best_direction(state: &GameState, budget: Budget, seed: u64) -> Direction {
+    let config = MctsConfig {
+        budget,
+        seed,
+        ..MctsConfig::default()
+    };
+    Mcts::new(config).best_direction(state)
+}
@@ -0,0 +1,508 @@
+use core::ops::Range;
+
+use crate::codec::{ByteReader, ByteWriter, DecodeError};
+use crate::config::GameConfig;
+use crate::game_helpers::Position;
+use crate::game_modes::GameMode;
+use crate::game_state::GameState;
+use crate::ghost_state::GhostState;
+use crate::location::LocationState;
+use crate::maze::{find_static, Maze};
+use crate::variables::{EMPTY_LOC, MAZE_COLS, MAZE_ROWS};
+
+/// The bytes that every snapshot starts with
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"PBSS";
+
+/// The current version of the snapshot format
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// The length of a snapshot, in bytes
+pub const SNAPSHOT_LEN: usize = 775;
+
+/// The length of an encoded [`GameConfig`], in bytes
+pub(crate) const CONFIG_LEN: usize = 562;
+
+// The lengths of an encoded location and ghost, in bytes
+const LOCATION_LEN: usize = 3;
+const GHOST_LEN: usize = 3 * LOCATION_LEN + 4;
+
+/*
+A snapshot captures the entire engine state, unlike the packets shared with the
+server, which drop the seed, the planned ghost locations and the rules. Decoding
+a snapshot gives back an identical game state, which has an identical future.
+*/
+
+fn write_location(w: &mut ByteWriter, loc: LocationState) {
+    w.i8(loc.row);
+    w.i8(loc.col);
+    w.u8(loc.dir as u8);
+}
+
+// Read a position whose row and column must each lie in a range, unless it is empty
+fn read_position(
+    r: &mut ByteReader,
+    field: &'static str,
+    rows: Range<i8>,
+    cols: Range<i8>,
+) -> Result<Position, DecodeError> {
+    let offset = r.offset();
+    let pos = (r.i8(field)?, r.i8(field)?);
+    if pos == EMPTY_LOC.get_coords() {
+        return Ok(pos);
+    }
+    let invalid = |i: usize, value: i8| DecodeError::InvalidValue {
+        field,
+        offset: offset + i,
+        value: value as u8,
+    };
+    if !rows.contains(&pos.0) {
+        return Err(invalid(0, pos.0));
+    }
+    if !cols.contains(&pos.1) {
+        return Err(invalid(1, pos.1));
+    }
+    Ok(pos)
+}
+
+// Read a location, which must be on the maze or empty (so that moving it one step in
+// any direction can't overflow)
+fn read_location(r: &mut ByteReader, field: &'static str) -> Result<LocationState, DecodeError> {
+    let (row, col) = read_position(r, field, 0..MAZE_ROWS as i8, 0..MAZE_COLS as i8)?;
+    let dir = r.parse(field, |x| x.try_into().ok())?;
+    Ok(LocationState { row, col, dir })
+}
+
+// Read a scatter target, which may be off the maze, but no further from it than the
+// size of the maze
+fn read_target(r: &mut ByteReader, field: &'static str) -> Result<LocationState, DecodeError> {
+    let rows = -(MAZE_ROWS as i8)..2 * MAZE_ROWS as i8;
+    let cols = -(MAZE_COLS as i8)..2 * MAZE_COLS as i8;
+    let (row, col) = read_position(r, field, rows, cols)?;
+    let dir = r.parse(field, |x| x.try_into().ok())?;
+    Ok(LocationState { row, col, dir })
+}
+
+fn write_rows(w: &mut ByteWriter, rows: &[u32; MAZE_ROWS]) {
+    for row in rows {
+        w.u32(*row);
+    }
+}
+
+fn read_rows(r: &mut ByteReader, field: &'static str) -> Result<[u32; MAZE_ROWS], DecodeError> {
+    let mut rows = [0; MAZE_ROWS];
+    for row in &mut rows {
+        *row = r.u32(field)?;
+    }
+    Ok(rows)
+}
+
+fn write_mode(w: &mut ByteWriter, mode: GameMode) {
+    w.u8(match mode {
+        GameMode::SCATTER => 0,
+        GameMode::CHASE => 1,
+    });
+}
+
+fn read_mode(r: &mut ByteReader, field: &'static str) -> Result<GameMode, DecodeError> {
+    r.parse(field, |x| match x {
+        0 => Some(GameMode::SCATTER),
+        1 => Some(GameMode::CHASE),
+        _ => None,
+    })
+}
+
+fn read_bool(r: &mut ByteReader, field: &'static str) -> Result<bool, DecodeError> {
+    r.parse(field, |x| match x {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    })
+}
+
+fn read_update_period(r: &mut ByteReader, field: &'static str) -> Result<u8, DecodeError> {
+    r.parse(field, |x| (x != 0).then_some(x))
+}
+
+fn write_maze(w: &mut ByteWriter, maze: &Maze) {
+    write_rows(w, &maze.walls);
+    write_rows(w, &maze.pellets);
+    write_rows(w, &maze.super_pellets);
+    write_rows(w, &maze.ghost_house);
+    w.u16(maze.num_pellets);
+    w.i8(maze.ghost_house_exit_pos.0);
+    w.i8(maze.ghost_house_exit_pos.1);
+    write_location(w, maze.pacman_spawn_loc);
+    write_location(w, maze.fruit_spawn_loc);
+    for loc in maze.ghost_spawn_locs {
+        write_location(w, loc);
+    }
+}
+
+fn read_maze(r: &mut ByteReader, mazes: &[&'static Maze]) -> Result<&'static Maze, DecodeError> {
+    let maze = Maze {
+        walls: read_rows(r, "maze walls")?,
+        pellets: read_rows(r, "maze pellets")?,
+        super_pellets: read_rows(r, "maze super pellets")?,
+        ghost_house: read_rows(r, "maze ghost house")?,
+        num_pellets: r.u16("maze num_pellets")?,
+        ghost_house_exit_pos: read_position(
+            r,
+            "ghost house exit",
+            0..MAZE_ROWS as i8,
+            0..MAZE_COLS as i8,
+        )?,
+        pacman_spawn_loc: read_location(r, "pacman spawn")?,
+        fruit_spawn_loc: read_location(r, "fruit spawn")?,
+        ghost_spawn_locs: [
+            read_location(r, "ghost spawn")?,
+            read_location(r, "ghost spawn")?,
+            read_location(r, "ghost spawn")?,
+            read_location(r, "ghost spawn")?,
+        ],
+    };
+    find_static(&maze, mazes).ok_or(DecodeError::UnknownMaze)
+}
+
+pub(crate) fn write_config(w: &mut ByteWriter, config: &GameConfig) {
+    let start = w.offset();
+    w.u8(config.init_update_period);
+    w.u16(config.level_duration);
+    w.u16(config.level_penalty_duration);
+    write_mode(w, config.init_mode);
+    w.u8(config.scatter_duration);
+    w.u8(config.chase_duration);
+    w.u8(config.init_level);
+    w.u8(config.init_lives);
+    write_maze(w, config.maze);
+    w.u8(config.fruit_duration);
+    w.u16(config.fruit_points);
+    for loc in config.ghost_scatter_targets {
+        write_location(w, loc);
+    }
+    w.bytes(&config.ghost_trapped_steps);
+    w.u8(config.ghost_fright_steps);
+    w.u16(config.fruit_threshold1);
+    w.u16(config.fruit_threshold2);
+    w.u16(config.anger_threshold1);
+    w.u16(config.anger_threshold2);
+    w.u16(config.pellet_points);
+    w.u16(config.super_pellet_points);
+    w.u16(config.combo_multiplier);
+    debug_assert_eq!(w.offset() - start, CONFIG_LEN);
+}
+
+// Decode a config, whose maze must be the standard maze, one of the given mazes, or a
+// registered maze
+pub(crate) fn read_config(
+    r: &mut ByteReader,
+    mazes: &[&'static Maze],
+) -> Result<GameConfig, DecodeError> {
+    Ok(GameConfig {
+        init_update_period: read_update_period(r, "init_update_period")?,
+        level_duration: r.u16("level_duration")?,
+        level_penalty_duration: r.u16("level_penalty_duration")?,
+        init_mode: read_mode(r, "init_mode")?,
+        scatter_duration: r.u8("scatter_duration")?,
+        chase_duration: r.u8("chase_duration")?,
+        init_level: r.u8("init_level")?,
+        init_lives: r.u8("init_lives")?,
+        maze: read_maze(r, mazes)?,
+        fruit_duration: r.u8("fruit_duration")?,
+        fruit_points: r.u16("fruit_points")?,
+        ghost_scatter_targets: [
+            read_target(r, "ghost scatter target")?,
+            read_target(r, "ghost scatter target")?,
+            read_target(r, "ghost scatter target")?,
+            read_target(r, "ghost scatter target")?,
+        ],
+        ghost_trapped_steps: r.bytes("ghost_trapped_steps")?,
+        ghost_fright_steps: r.u8("ghost_fright_steps")?,
+        fruit_threshold1: r.u16("fruit_threshold1")?,
+        fruit_threshold2: r.u16("fruit_threshold2")?,
+        anger_threshold1: r.u16("anger_threshold1")?,
+        anger_threshold2: r.u16("anger_threshold2")?,
+        pellet_points: r.u16("pellet_points")?,
+        super_pellet_points: r.u16("super_pellet_points")?,
+        combo_multiplier: r.u16("combo_multiplier")?,
+    })
+}
+
+fn write_ghost(w: &mut ByteWriter, ghost: &GhostState) {
+    let start = w.offset();
+    write_location(w, ghost.loc);
+    write_location(w, ghost.next_loc);
+    write_location(w, ghost.scatter_target);
+    w.u8(ghost.color as u8);
+    w.u8(ghost.trapped_steps);
+    w.u8(ghost.fright_steps);
+    w.u8(ghost.spawning as u8 | (ghost.eaten as u8) << 1);
+    debug_assert_eq!(w.offset() - start, GHOST_LEN);
+}
+
+// Read a ghost, whose color must match its place in the order red, pink, cyan, orange
+fn read_ghost(r: &mut ByteReader, index: u8) -> Result<GhostState, DecodeError> {
+    let loc = read_location(r, "ghost location")?;
+    let next_loc = read_location(r, "ghost next location")?;
+    let scatter_target = read_target(r, "ghost scatter target")?;
+    let color = r.parse("ghost color", |x| {
+        if x == index {
+            x.try_into().ok()
+        } else {
+            None
+        }
+    })?;
+    let trapped_steps = r.u8("ghost trapped_steps")?;
+    let fright_steps = r.u8("ghost fright_steps")?;
+    let flags = r.parse("ghost flags", |x| (x < 4).then_some(x))?;
+    Ok(GhostState {
+        loc,
+        next_loc,
+        scatter_target,
+        color,
+        trapped_steps,
+        fright_steps,
+        spawning: flags & 1 != 0,
+        eaten: flags & 2 != 0,
+    })
+}
+
+impl GameState {
+    /// Encodes the entire game state into a snapshot, which [`GameState::from_snapshot`]
+    /// restores exactly.
+    pub fn to_snapshot(&self) -> [u8; SNAPSHOT_LEN] {
+        let mut buf = [0; SNAPSHOT_LEN];
+        self.encode_snapshot_into(&mut buf);
+        buf
+    }
+
+    /// Encodes the entire game state into a snapshot buffer.
+    pub fn encode_snapshot_into(&self, buf: &mut [u8; SNAPSHOT_LEN]) {
+        let mut w = ByteWriter::new(buf);
+
+        // Header
+        w.bytes(&SNAPSHOT_MAGIC);
+        w.u8(SNAPSHOT_VERSION);
+
+        // General game info
+        w.u32(self.curr_ticks);
+        w.u8(self.update_period);
+        write_mode(&mut w, self.mode);
+        w.u8(self.paused as u8);
+        w.u8(self.mode_steps);
+        w.u16(self.level_steps);
+        w.u16(self.curr_score);
+        w.u8(self.curr_level);
+        w.u8(self.curr_lives);
+
+        // Pacman and fruit
+        write_location(&mut w, self.pacman_loc);
+        write_location(&mut w, self.fruit_loc);
+        w.u8(self.fruit_steps);
+
+        // Ghosts
+        for ghost in &self.ghosts {
+            write_ghost(&mut w, ghost);
+        }
+        w.u8(self.ghost_combo);
+
+        // Pellets
+        write_rows(&mut w, &self.pellets);
+        w.u16(self.num_pellets);
+
+        // Auxiliary info
+        w.u64(self.seed);
+        write_config(&mut w, &self.config);
+
+        debug_assert_eq!(w.offset(), SNAPSHOT_LEN);
+    }
+
+    /// Decodes a game state from a snapshot made by [`GameState::to_snapshot`].
+    ///
+    /// The snapshot's maze must be the standard maze or a registered maze (see
+    /// [`Maze::register`]), or else decoding fails with [`DecodeError::UnknownMaze`].
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::from_snapshot_with_mazes(bytes, &[])
+    }
+
+    /// Decodes a game state from a snapshot, whose maze may also be any of the given
+    /// mazes (which works without `std`).
+    pub fn from_snapshot_with_mazes(
+        bytes: &[u8],
+        mazes: &[&'static Maze],
+    ) -> Result<Self, DecodeError> {
+        let mut r = ByteReader::new(bytes);
+
+        // Header
+        let offset = r.offset();
+        let magic: [u8; 4] = r.bytes("magic")?;
+        if let Some(i) = (0..4).find(|&i| magic[i] != SNAPSHOT_MAGIC[i]) {
+            return Err(DecodeError::InvalidValue {
+                field: "magic",
+                offset: offset + i,
+                value: magic[i],
+            });
+        }
+        let version = r.u8("version")?;
+        if version != SNAPSHOT_VERSION {
+            return Err(DecodeError::UnsupportedVersion { version });
+        }
+        if bytes.len() != SNAPSHOT_LEN {
+            return Err(DecodeError::WrongLength {
+                expected: SNAPSHOT_LEN,
+                actual: bytes.len(),
+            });
+        }
+
+        // General game info
+        let curr_ticks = r.u32("curr_ticks")?;
+        let update_period = read_update_period(&mut r, "update_period")?;
+        let mode = read_mode(&mut r, "mode")?;
+        let paused = read_bool(&mut r, "paused")?;
+        let mode_steps = r.u8("mode_steps")?;
+        let level_steps = r.u16("level_steps")?;
+        let curr_score = r.u16("curr_score")?;
+        let curr_level = r.u8("curr_level")?;
+        let curr_lives = r.u8("curr_lives")?;
+
+        // Pacman and fruit
+        let pacman_offset = r.offset();
+        let pacman_loc = read_location(&mut r, "pacman location")?;
+        let fruit_loc = read_location(&mut r, "fruit location")?;
+        let fruit_steps = r.u8("fruit_steps")?;
+
+        // Ghosts
+        let ghosts_offset = r.offset();
+        let ghosts = [
+            read_ghost(&mut r, 0)?,
+            read_ghost(&mut r, 1)?,
+            read_ghost(&mut r, 2)?,
+            read_ghost(&mut r, 3)?,
+        ];
+        let ghost_combo = r.u8("ghost_combo")?;
+
+        // Pellets
+        let pellets = read_rows(&mut r, "pellets")?;
+        let num_pellets = r.u16("num_pellets")?;
+
+        // Auxiliary info
+        let seed = r.u64("seed")?;
+        let config = read_config(&mut r, mazes)?;
+
+        // Now that the maze is known, check that Pacman and the ghosts are somewhere
+        // that they can be
+        let maze = config.maze;
+        r.check_open(
+            "pacman location",
+            pacman_offset,
+            pacman_loc.get_coords(),
+            |pos| !maze.wall_at(pos),
+        )?;
+        for (i, ghost) in ghosts.iter().enumerate() {
+            let offset = ghosts_offset + i * GHOST_LEN;
+            let next_offset = offset + LOCATION_LEN;
+            let open = |pos| maze.open_to_ghost(pos, ghost.spawning);
+            r.check_open("ghost location", offset, ghost.loc.get_coords(), open)?;
+            r.check_open(
+                "ghost next location",
+                next_offset,
+                ghost.next_loc.get_coords(),
+                open,
+            )?;
+
+            // The ghost must also face somewhere that it can move to, since ghosts can't
+            // turn back
+            let next_loc = ghost.next_loc;
+            if !next_loc.is_empty() && !open(next_loc.get_ahead_coords(1)) {
+                return Err(DecodeError::InvalidValue {
+                    field: "ghost next location",
+                    offset: next_offset + 2,
+                    value: next_loc.dir as u8,
+                });
+            }
+        }
+
+        Ok(Self {
+            curr_ticks,
+            update_period,
+            mode,
+            paused,
+            mode_steps,
+            level_steps,
+            curr_score,
+            curr_level,
+            curr_lives,
+            pacman_loc,
+            fruit_loc,
+            fruit_steps,
+            ghosts,
+            ghost_combo,
+            pellets,
+            num_pellets,
+            seed,
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::location::Direction;
+
+    // Play a game with random moves for a number of ticks
+    fn play(state: &mut GameState, rng: &mut SmallRng, ticks: u32) {
+        for _ in 0..ticks {
+            if state.update_ready() {
+                let dir: u8 = rng.gen_range(0..5);
+                state.move_pacman_dir(Direction::try_from(dir).unwrap());
+            }
+            state.step();
+        }
+    }
+
+    #[test]
+    fn round_trip_has_identical_future() {
+        let mut state = GameState::new_with_seed(7);
+        state.paused = false;
+        play(&mut state, &mut SmallRng::seed_from_u64(1), 3000);
+        assert!(!state.is_game_over());
+
+        let mut decoded = GameState::from_snapshot(&state.to_snapshot()).unwrap();
+        assert_eq!(decoded, state);
+
+        play(&mut state, &mut SmallRng::seed_from_u64(2), 3000);
+        play(&mut decoded, &mut SmallRng::seed_from_u64(2), 3000);
+        assert_eq!(decoded, state);
+    }
+
+    #[test]
+    fn rejects_location_off_maze() {
+        let mut state = GameState::new_with_seed(7);
+        state.pacman_loc.row = 127;
+        assert!(matches!(
+            GameState::from_snapshot(&state.to_snapshot()),
+            Err(DecodeError::InvalidValue {
+                field: "pacman location",
+                value: 127,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_location_in_wall() {
+        let mut state = GameState::new_with_seed(7);
+        state.pacman_loc.update_coords((0, 0));
+        assert!(matches!(
+            GameState::from_snapshot(&state.to_snapshot()),
+            Err(DecodeError::InvalidValue {
+                field: "pacman location",
+                value: 0,
+                ..
+            })
+        ));
+    }
+}
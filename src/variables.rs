@@ -6,6 +6,9 @@ pub const MAZE_ROWS: usize = 31;
 /// The number of columns in the pellets and walls states
 pub const MAZE_COLS: usize = 28;
 
+/// The number of ticks that the game advances per second
+pub const GAME_FPS: u8 = 24;
+
 /// The update period that the game starts with by default
 pub const INIT_UPDATE_PERIOD: u8 = 12;
 
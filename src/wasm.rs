@@ -0,0 +1,190 @@
+//! JavaScript bindings, for running the engine in the browser.
+//!
+//! Directions are passed as integers (0 = up, 1 = left, 2 = down, 3 = right, 4 = stay).
+//! Locations are read out as `[row, col]` pairs, flattened into typed arrays.
+//! Events are returned as their names (see [`GameEvent::name`]).
+//!
+//! [`GameEvent::name`]: crate::events::GameEvent::name
+
+use wasm_bindgen::prelude::*;
+
+use crate::game_state::GameState;
+use crate::location::{Direction, LocationState};
+use crate::obs::OBS_LEN;
+use crate::variables::{MAZE_COLS, MAZE_ROWS};
+
+// The values of the cells in `WasmGame::board`
+const CELL_EMPTY: u8 = 0;
+const CELL_WALL: u8 = 1;
+const CELL_PELLET: u8 = 2;
+const CELL_SUPER_PELLET: u8 = 3;
+
+fn coords(loc: LocationState) -> [i8; 2] {
+    [loc.row, loc.col]
+}
+
+/// A game, exported to JavaScript as `Game`.
+#[wasm_bindgen(js_name = Game)]
+#[derive(Clone)]
+pub struct WasmGame {
+    state: GameState,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    /// Creates a new game, with a random seed if none is given
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: Option<u64>) -> Self {
+        let state = match seed {
+            Some(seed) => GameState::new_with_seed(seed),
+            None => GameState::new(),
+        };
+        Self { state }
+    }
+
+    /// Decodes a game state in the same format as the server
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8], seed: u64) -> Result<WasmGame, JsError> {
+        let state = GameState::from_bytes(bytes, seed)?;
+        Ok(Self { state })
+    }
+
+    /// Encodes the game state in the same format as the server
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.state.to_bytes()
+    }
+
+    /// Creates a copy of the game
+    #[wasm_bindgen(js_name = clone)]
+    pub fn clone_game(&self) -> WasmGame {
+        self.clone()
+    }
+
+    /// Advances the game by one tick, returning the events that happened
+    pub fn step(&mut self) -> Vec<String> {
+        self.state.step().names()
+    }
+
+    /// Moves Pacman in a direction, returning the events that happened
+    #[wasm_bindgen(js_name = movePacman)]
+    pub fn move_pacman(&mut self, dir: u8) -> Result<Vec<String>, JsError> {
+        let dir = Direction::try_from(dir)?;
+        Ok(self.state.move_pacman_dir(dir).names())
+    }
+
+    /// Moves Pacman, then advances to the next update, returning whether the game is over
+    #[wasm_bindgen(js_name = stepWithAction)]
+    pub fn step_with_action(&mut self, dir: u8) -> Result<bool, JsError> {
+        let dir = Direction::try_from(dir)?;
+        Ok(self.state.step_with_action(dir).game_over)
+    }
+
+    /// Sets Pacman's location, returning the events that happened along the way, or
+    /// throwing if Pacman can't be moved there
+    #[wasm_bindgen(js_name = setPacmanLocation)]
+    pub fn set_pacman_location(&mut self, row: i8, col: i8) -> Result<Vec<String>, JsError> {
+        Ok(self.state.set_pacman_location((row, col))?.names())
+    }
+
+    /// The board, row by row: 0 for empty cells, 1 for walls, 2 for pellets and 3 for
+    /// super pellets
+    pub fn board(&self) -> Vec<u8> {
+        let mut board = vec![CELL_EMPTY; MAZE_ROWS * MAZE_COLS];
+        for (row, cells) in board.chunks_exact_mut(MAZE_COLS).enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                let pos = (row as i8, col as i8);
+                *cell = if self.state.wall_at(pos) {
+                    CELL_WALL
+                } else if !self.state.pellet_at(pos) {
+                    CELL_EMPTY
+                } else if self.state.config.maze.super_pellet_at(pos) {
+                    CELL_SUPER_PELLET
+                } else {
+                    CELL_PELLET
+                };
+            }
+        }
+        board
+    }
+
+    /// The wall bitfields, one per row, with column 0 as the lowest bit
+    pub fn walls(&self) -> Vec<u32> {
+        self.state.config.maze.walls.to_vec()
+    }
+
+    /// The pellet bitfields, one per row, with column 0 as the lowest bit
+    pub fn pellets(&self) -> Vec<u32> {
+        self.state.pellets.to_vec()
+    }
+
+    /// Pacman's location
+    pub fn pacman(&self) -> Vec<i8> {
+        coords(self.state.pacman_loc).to_vec()
+    }
+
+    /// The fruit's location, which is empty if there is no fruit
+    pub fn fruit(&self) -> Vec<i8> {
+        match self.state.fruit_exists() {
+            true => coords(self.state.fruit_loc).to_vec(),
+            false => Vec::new(),
+        }
+    }
+
+    /// The locations of the ghosts, in the order red, pink, cyan, orange
+    pub fn ghosts(&self) -> Vec<i8> {
+        self.state
+            .ghosts
+            .iter()
+            .flat_map(|g| coords(g.loc))
+            .collect()
+    }
+
+    /// The steps that each ghost stays frightened for, in the same order as `ghosts`
+    #[wasm_bindgen(js_name = ghostFrightSteps)]
+    pub fn ghost_fright_steps(&self) -> Vec<u8> {
+        self.state.ghosts.iter().map(|g| g.fright_steps).collect()
+    }
+
+    /// Encodes the game state as planes for a neural network
+    pub fn obs(&self) -> Vec<f32> {
+        let mut buf = vec![0.0; OBS_LEN];
+        self.state.encode_obs((&mut buf[..]).try_into().unwrap());
+        buf
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ticks(&self) -> u32 {
+        self.state.curr_ticks
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn paused(&self) -> bool {
+        self.state.paused
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_paused(&mut self, paused: bool) {
+        self.state.paused = paused;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn score(&self) -> u16 {
+        self.state.curr_score
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn level(&self) -> u8 {
+        self.state.curr_level
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn lives(&self) -> u8 {
+        self.state.curr_lives
+    }
+
+    #[wasm_bindgen(getter, js_name = gameOver)]
+    pub fn game_over(&self) -> bool {
+        self.state.is_game_over()
+    }
+}